@@ -1,4 +1,4 @@
-use amethyst::{Application, GameData, SimpleState, SimpleTrans, StateData, StateEvent, assets::LoaderBundle, core::{
+use amethyst::{Application, GameData, SimpleState, SimpleTrans, StateData, StateEvent, assets::{DefaultLoader, Loader, LoaderBundle, ProcessingQueue}, core::{
         dispatcher::DispatcherBuilder,
         transform::TransformBundle,
     }, ecs::{ParallelRunnable, System, SystemBuilder}, input::{
@@ -6,8 +6,10 @@ use amethyst::{Application, GameData, SimpleState, SimpleTrans, StateData, State
         is_key_down,
         VirtualKeyCode,
     }, renderer::{
+        loaders::load_from_srgba,
+        palette::Srgba,
         rendy::hal::command::ClearColor,
-        types::DefaultBackend,
+        types::{DefaultBackend, TextureData},
         RenderToWindow,
         RenderingBundle,
     }, utils::application_root_dir};
@@ -26,7 +28,18 @@ impl SimpleState for HelloWorldState {
     fn on_start(&mut self, data: StateData<'_, GameData>) {
         log::debug!("Staring ExampleStart");
 
-        data.resources.insert(UiState::default());
+        let texture = {
+            let loader = data.resources.get::<DefaultLoader>().unwrap();
+            let texture_storage = data.resources.get::<ProcessingQueue<TextureData>>().unwrap();
+            let texture_data: TextureData = load_from_srgba(Srgba::new(0.8, 0.3, 0.1, 1.0)).into();
+            loader.load_from_data(texture_data, (), &texture_storage)
+        };
+        let texture_id = data.resources.get_mut::<EguiContext>().unwrap().register_texture(texture);
+
+        data.resources.insert(UiState {
+            texture_id: Some(texture_id),
+            ..Default::default()
+        });
 
         let mut _egui_config = data.resources.get_mut::<EguiConfig>().unwrap();
         //egui_config.allow_webbrowser = true;
@@ -49,7 +62,7 @@ struct UiState {
     label: String,
     value: f32,
     painting: Painting,
-    inverted: bool,
+    texture_id: Option<egui::TextureId>,
 }
 
 struct HelloWorldSystem;
@@ -58,102 +71,90 @@ impl System for HelloWorldSystem {
     fn build(self) -> Box<dyn ParallelRunnable + 'static> {
         Box::new(
             SystemBuilder::new("HelloWorldSystem")
-                .read_resource::<EguiContext>()
+                .write_resource::<EguiContext>()
                 .write_resource::<UiState>()
                 .build(|_commands, _world, (egui_ctx, ui_state), _queries| {
-                    let ctx = egui_ctx.ctx().unwrap();
-
-                    /*let mut load = false;
-                    let mut remove = false;
-                    let mut invert = false;*/
+                    let remove = egui_ctx.with_ui(|ctx| {
+                        let mut remove = false;
+
+                        egui::SidePanel::left("side_panel")
+                            .default_width(200.0)
+                            .show(ctx, |ui| {
+                                ui.heading("Side Panel");
+
+                                ui.horizontal(|ui| {
+                                    ui.label("Write something: ");
+                                    ui.text_edit_singleline(&mut ui_state.label);
+                                });
+
+                                ui.add(egui::Slider::new(&mut ui_state.value, 0.0..=10.0).text("value"));
+                                if ui.button("Increment").clicked() {
+                                    ui_state.value += 1.0;
+                                }
 
-                    egui::SidePanel::left("side_panel")
-                        .default_width(200.0)
-                        .show(ctx, |ui| {
-                            ui.heading("Side Panel");
+                                ui.allocate_space(egui::Vec2::new(1.0, 100.0));
+                                if let Some(texture_id) = ui_state.texture_id {
+                                    remove = ui.button("Remove").clicked();
+                                    ui.add(egui::widgets::Image::new(texture_id, [128.0, 128.0]));
+                                }
 
-                            ui.horizontal(|ui| {
-                                ui.label("Write something: ");
-                                ui.text_edit_singleline(&mut ui_state.label);
+                                ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
+                                    ui.add(
+                                        egui::Hyperlink::new("https://github.com/emilk/egui/").text("powered by egui"),
+                                    );
+                                });
                             });
 
-                            ui.add(egui::Slider::new(&mut ui_state.value, 0.0..=10.0).text("value"));
-                            if ui.button("Increment").clicked() {
-                                ui_state.value += 1.0;
-                            }
-
-                            ui.allocate_space(egui::Vec2::new(1.0, 100.0));
-                            // TODO: User textures
-                            /*ui.horizontal(|ui| {
-                                load = ui.button("Load").clicked();
-                                invert = ui.button("Invert").clicked();
-                                remove = ui.button("Remove").clicked();
-                            });*/
-
-                            /*ui.add(egui::widgets::Image::new(
-                                egui::TextureId::User(BEVY_TEXTURE_ID),
-                                [256.0, 256.0],
-                            ));*/
-
-                            ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
-                                ui.add(
-                                    egui::Hyperlink::new("https://github.com/emilk/egui/").text("powered by egui"),
-                                );
+                        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+                            // The top panel is often a good place for a menu bar:
+                            egui::menu::bar(ui, |ui| {
+                                egui::menu::menu(ui, "File", |ui| {
+                                    if ui.button("Quit").clicked() {
+                                        std::process::exit(0);
+                                    }
+                                });
                             });
                         });
 
-                    egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-                        // The top panel is often a good place for a menu bar:
-                        egui::menu::bar(ui, |ui| {
-                            egui::menu::menu(ui, "File", |ui| {
-                                if ui.button("Quit").clicked() {
-                                    std::process::exit(0);
-                                }
+                        egui::CentralPanel::default().show(ctx, |ui| {
+                            ui.heading("Egui Template");
+                            ui.hyperlink("https://github.com/emilk/egui_template");
+                            ui.add(egui::github_link_file_line!(
+                                "https://github.com/emilk/egui_template/blob/master/",
+                                "Direct link to source code."
+                            ));
+                            egui::warn_if_debug_build(ui);
+
+                            ui.separator();
+
+                            ui.heading("Central Panel");
+                            ui.label("The central panel is the region left after adding TopPanel's and SidePanel's");
+                            ui.label("It is often a great place for big things, like drawings:");
+
+                            ui.heading("Draw with your mouse to paint:");
+                            ui_state.painting.ui_control(ui);
+                            egui::Frame::dark_canvas(ui.style()).show(ui, |ui| {
+                                ui_state.painting.ui_content(ui);
                             });
                         });
-                    });
 
-                    egui::CentralPanel::default().show(ctx, |ui| {
-                        ui.heading("Egui Template");
-                        ui.hyperlink("https://github.com/emilk/egui_template");
-                        ui.add(egui::github_link_file_line!(
-                            "https://github.com/emilk/egui_template/blob/master/",
-                            "Direct link to source code."
-                        ));
-                        egui::warn_if_debug_build(ui);
-
-                        ui.separator();
-
-                        ui.heading("Central Panel");
-                        ui.label("The central panel is the region left after adding TopPanel's and SidePanel's");
-                        ui.label("It is often a great place for big things, like drawings:");
-
-                        ui.heading("Draw with your mouse to paint:");
-                        ui_state.painting.ui_control(ui);
-                        egui::Frame::dark_canvas(ui.style()).show(ui, |ui| {
-                            ui_state.painting.ui_content(ui);
-                        });
-                    });
+                        egui::Window::new("Window")
+                            .scroll(true)
+                            .show(ctx, |ui| {
+                                ui.label("Windows can be moved by dragging them.");
+                                ui.label("They are automatically sized based on contents.");
+                                ui.label("You can turn on resizing and scrolling if you like.");
+                                ui.label("You would normally chose either panels OR windows.");
+                            });
 
-                    egui::Window::new("Window")
-                        .scroll(true)
-                        .show(ctx, |ui| {
-                            ui.label("Windows can be moved by dragging them.");
-                            ui.label("They are automatically sized based on contents.");
-                            ui.label("You can turn on resizing and scrolling if you like.");
-                            ui.label("You would normally chose either panels OR windows.");
-                        });
+                        remove
+                    });
 
-                    /*
-                    if invert {
-                        ui_state.inverted = !ui_state.inverted;
-                    }
-                    if load || invert {
-                        todo!()
+                    if remove == Some(true) {
+                        if let Some(texture_id) = ui_state.texture_id.take() {
+                            egui_ctx.unregister_texture(texture_id);
+                        }
                     }
-                    if remove {
-                        todo!()
-                    }*/
                 }),
         )
     }
@@ -230,8 +231,7 @@ fn main() -> Result<(), amethyst_error::Error> {
     dispatcher.add_bundle(LoaderBundle);
     dispatcher.add_bundle(TransformBundle);
     dispatcher.add_bundle(InputBundle::new());
-    dispatcher.add_bundle(EguiBundle::default());
-    dispatcher.add_system(HelloWorldSystem);
+    dispatcher.add_bundle(EguiBundle::new().with_ui_system(HelloWorldSystem));
     dispatcher.add_bundle(
         RenderingBundle::<DefaultBackend>::new()
             .with_plugin(
@@ -239,7 +239,7 @@ fn main() -> Result<(), amethyst_error::Error> {
                     float32: [0.1, 0.03, 0.35, 1.0],
                 }),
             )
-            .with_plugin(RenderEgui::default()),
+            .with_plugin(RenderEgui::new()),
     );
 
     let game = Application::build(assets_directory, HelloWorldState)?.build(dispatcher)?;
@@ -1,4 +1,7 @@
-
+# Generates `KEY_MAP` and `UNMAPPED` in `src/system.rs`.
+#
+# Every egui `Key` must appear at least once. winit keys that are not mapped have no egui
+# counterpart and are listed in `UNMAPPED` (see the documentation of `KEY_MAP`).
 
 egui_keys = [
     "ArrowDown",
@@ -53,24 +56,197 @@ egui_keys = [
     "Y",
     "Z",
 ]
+# Every winit `VirtualKeyCode`, in declaration order.
+winit_keys = [
+    "Key1",
+    "Key2",
+    "Key3",
+    "Key4",
+    "Key5",
+    "Key6",
+    "Key7",
+    "Key8",
+    "Key9",
+    "Key0",
+    "A",
+    "B",
+    "C",
+    "D",
+    "E",
+    "F",
+    "G",
+    "H",
+    "I",
+    "J",
+    "K",
+    "L",
+    "M",
+    "N",
+    "O",
+    "P",
+    "Q",
+    "R",
+    "S",
+    "T",
+    "U",
+    "V",
+    "W",
+    "X",
+    "Y",
+    "Z",
+    "Escape",
+    "F1",
+    "F2",
+    "F3",
+    "F4",
+    "F5",
+    "F6",
+    "F7",
+    "F8",
+    "F9",
+    "F10",
+    "F11",
+    "F12",
+    "F13",
+    "F14",
+    "F15",
+    "F16",
+    "F17",
+    "F18",
+    "F19",
+    "F20",
+    "F21",
+    "F22",
+    "F23",
+    "F24",
+    "Snapshot",
+    "Scroll",
+    "Pause",
+    "Insert",
+    "Home",
+    "Delete",
+    "End",
+    "PageDown",
+    "PageUp",
+    "Left",
+    "Up",
+    "Right",
+    "Down",
+    "Back",
+    "Return",
+    "Space",
+    "Compose",
+    "Caret",
+    "Numlock",
+    "Numpad0",
+    "Numpad1",
+    "Numpad2",
+    "Numpad3",
+    "Numpad4",
+    "Numpad5",
+    "Numpad6",
+    "Numpad7",
+    "Numpad8",
+    "Numpad9",
+    "NumpadAdd",
+    "NumpadDivide",
+    "NumpadDecimal",
+    "NumpadComma",
+    "NumpadEnter",
+    "NumpadEquals",
+    "NumpadMultiply",
+    "NumpadSubtract",
+    "AbntC1",
+    "AbntC2",
+    "Apostrophe",
+    "Apps",
+    "Asterisk",
+    "At",
+    "Ax",
+    "Backslash",
+    "Calculator",
+    "Capital",
+    "Colon",
+    "Comma",
+    "Convert",
+    "Equals",
+    "Grave",
+    "Kana",
+    "Kanji",
+    "LAlt",
+    "LBracket",
+    "LControl",
+    "LShift",
+    "LWin",
+    "Mail",
+    "MediaSelect",
+    "MediaStop",
+    "Minus",
+    "Mute",
+    "MyComputer",
+    "NavigateForward",
+    "NavigateBackward",
+    "NextTrack",
+    "NoConvert",
+    "OEM102",
+    "Period",
+    "PlayPause",
+    "Plus",
+    "Power",
+    "PrevTrack",
+    "RAlt",
+    "RBracket",
+    "RControl",
+    "RShift",
+    "RWin",
+    "Semicolon",
+    "Slash",
+    "Sleep",
+    "Stop",
+    "Sysrq",
+    "Tab",
+    "Underline",
+    "Unlabeled",
+    "VolumeDown",
+    "VolumeUp",
+    "Wake",
+    "WebBack",
+    "WebFavorites",
+    "WebForward",
+    "WebHome",
+    "WebRefresh",
+    "WebSearch",
+    "WebStop",
+    "Yen",
+    "Copy",
+    "Paste",
+    "Cut",
+]
 
-def to_amethyst_key(k):
+def to_winit_keys(k):
     if k.startswith("Num"):
-        return "Key" + k[3:]
+        return ["Key" + k[3:], "Numpad" + k[3:]]
     elif k.startswith("Arrow"):
-        return k[5:]
+        return [k[5:]]
     elif k == "Backspace":
-        return "Back"
+        return ["Back"]
     elif k == "Enter":
-        return "Return"
+        return ["Return", "NumpadEnter"]
     else:
-        return k
+        return [k]
 
-print("match key {")
-for k in egui_keys:
-    print("  VirtualKeyCode::{} => Some(Key::{}),".format(to_amethyst_key(k), k))
-print("  _ => None")
-print("}")
+mapped = []
 
-
-print(egui)
\ No newline at end of file
+print("const KEY_MAP: &[(VirtualKeyCode, Key)] = &[")
+for k in egui_keys:
+    for w in to_winit_keys(k):
+        mapped.append(w)
+        print("    (VirtualKeyCode::{}, Key::{}),".format(w, k))
+print("];")
+print()
+print("#[cfg(test)]")
+print("const UNMAPPED: &[VirtualKeyCode] = &[")
+for w in winit_keys:
+    if w not in mapped:
+        print("    VirtualKeyCode::{},".format(w))
+print("];")
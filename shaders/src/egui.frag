@@ -5,7 +5,13 @@
 
 layout(set = 1, binding = 0) uniform sampler2D albedo;
 
+// Egui's clip rectangle in points: (min.x, min.y, max.x, max.y).
+layout(push_constant) uniform Clip {
+    layout(offset = 64) vec4 clip_rect;
+};
+
 layout(location = 0) in VertexData {
+    vec2 pos;
     vec2 uv;
     vec4 color;
 } vertex;
@@ -14,6 +20,10 @@ layout(location = 0) out vec4 out_color;
 
 
 void main() {
+    if (any(lessThan(vertex.pos, clip_rect.xy)) || any(greaterThan(vertex.pos, clip_rect.zw))) {
+        discard;
+    }
+
     vec4 color = texture(albedo, vertex.uv) * vertex.color;
     if (color.a == 0.0) {
         discard;
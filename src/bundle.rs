@@ -1,47 +1,300 @@
+use std::{
+    marker::PhantomData,
+    path::Path,
+};
+
+use amethyst_config::{
+    Config,
+    ConfigError,
+};
 use amethyst_core::{
     dispatcher::DispatcherBuilder,
     ecs::{
+        ParallelRunnable,
         Resources,
+        System,
         SystemBundle,
         World,
     },
     EventChannel,
 };
 use amethyst_error::Error;
+use derivative::Derivative;
 use winit::event::Event;
 
-use crate::system::{
-    EguiConfig,
-    EguiContext,
-    EguiInputGrab,
-    EguiSystem,
+#[cfg(feature = "persistence")]
+use crate::persistence::{
+    EguiPersistence,
+    PersistenceSystem,
+};
+#[cfg(feature = "theme")]
+use crate::theme::{
+    EguiTheme,
+    EguiThemeSystem,
+};
+
+use crate::{
+    clipboard::EguiClipboard,
+    console::{
+        ConsoleCommands,
+        ConsoleSystem,
+        EguiConsole,
+    },
+    fonts::{
+        EguiFontSystem,
+        EguiFonts,
+    },
+    inspector::{
+        EguiEntityInspector,
+        EguiResourceInspector,
+        EntityInspectorSystem,
+        ResourceInspectorWindow,
+    },
+    pass::EguiRenderStats,
+    profiler::{
+        EguiProfiler,
+        ProfilerSystem,
+    },
+    speech::EguiSpeech,
+    system::{
+        DefaultEgui,
+        EguiConfig,
+        EguiContext,
+        EguiInputGrab,
+        EguiKey,
+        EguiSystem,
+    },
 };
 
-#[derive(Debug, Default)]
-pub struct EguiBundle;
+/// Sets up the egui context with key `K`. Add one bundle per context.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub struct EguiBundle<K = DefaultEgui> {
+    #[derivative(Debug = "ignore")]
+    ui_systems: Vec<Box<dyn ParallelRunnable>>,
+    offscreen_size: Option<(u32, u32)>,
+    entity_inspector: Option<EguiEntityInspector>,
+    resource_inspector: Option<EguiResourceInspector>,
+    console: Option<EguiConsole>,
+    profiler: Option<EguiProfiler>,
+    config: Option<EguiConfig>,
+    fonts: Option<EguiFonts>,
+    #[cfg(feature = "theme")]
+    theme: Option<EguiTheme>,
+    #[cfg(feature = "theme")]
+    without_theme: bool,
+    #[cfg(feature = "persistence")]
+    persistence: Option<EguiPersistence>,
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<K>,
+}
+
+impl EguiBundle<DefaultEgui> {
+    /// Creates a bundle for the default context. Use `EguiBundle::<K>::default()` for other
+    /// contexts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K: EguiKey> EguiBundle<K> {
+    /// Creates a bundle with the `EguiConfig` loaded from the RON file at `path`.
+    pub fn from_config_path(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Ok(Self::default().with_config(EguiConfig::load(path)?))
+    }
+
+    /// Sets the `EguiConfig` shared by all contexts. Without this, a config that is already in the
+    /// resources is kept, or the default config is used.
+    pub fn with_config(mut self, config: EguiConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Adds a system that builds UI. UI systems are scheduled right after `EguiSystem` started the
+    /// frame and before it is rendered, so `EguiContext::ctx` is always available to them.
+    ///
+    /// This relies on the dispatcher running systems that access the same resource in the order
+    /// they were added: both `EguiSystem<K>` and the UI system access `EguiContext<K>`, so the UI
+    /// system always runs after it. Rendering is thread local and runs after all systems. Systems
+    /// that are added to the dispatcher before this bundle run before the frame is started, so
+    /// `EguiContext::with_ui` returns `None` in them.
+    pub fn with_ui_system(mut self, system: impl System) -> Self {
+        self.ui_systems.push(system.build());
+        self
+    }
+
+    /// Lays out the UI for an image of `width` x `height` pixels, instead of the window. Use this
+    /// together with `RenderEgui::with_offscreen_target`, which renders into a render graph image
+    /// and not into a texture asset.
+    ///
+    /// The context doesn't receive mouse input from the window, inject it with e.g.
+    /// `EguiContext::inject_pointer_uv` instead.
+    pub fn with_offscreen_size(mut self, width: u32, height: u32) -> Self {
+        self.offscreen_size = Some((width, height));
+        self
+    }
+
+    /// Shows a window in this context that lists all entities and lets you edit their components.
+    /// Register your own component types with `EguiEntityInspector::with_component`.
+    pub fn with_entity_inspector(mut self, inspector: EguiEntityInspector) -> Self {
+        self.entity_inspector = Some(inspector);
+        self
+    }
+
+    /// Shows a window in this context that lets you view and edit resources. Register your own
+    /// resource types with `EguiResourceInspector::with_resource`.
+    ///
+    /// The window is shown by `RenderEgui<K>` right before rendering, so it needs that plugin.
+    pub fn with_resource_inspector(mut self, inspector: EguiResourceInspector) -> Self {
+        self.resource_inspector = Some(inspector);
+        self
+    }
+
+    /// Adds a developer console to this context. Commands are registered in the `ConsoleCommands`
+    /// resource.
+    pub fn with_console(mut self, console: EguiConsole) -> Self {
+        self.console = Some(console);
+        self
+    }
+
+    /// Shows frame times, egui's own cost and draw statistics of this context in a window.
+    pub fn with_profiler(mut self, profiler: EguiProfiler) -> Self {
+        self.profiler = Some(profiler);
+        self
+    }
+
+    /// Sets the fonts of all contexts. Fonts can also be changed later through the `EguiFonts`
+    /// resource.
+    pub fn with_fonts(mut self, fonts: EguiFonts) -> Self {
+        self.fonts = Some(fonts);
+        self
+    }
+
+    /// Sets the theme of all contexts. It can be switched later through the `EguiTheme` resource.
+    #[cfg(feature = "theme")]
+    pub fn with_theme(mut self, theme: EguiTheme) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Doesn't apply the `EguiTheme` to this context, so that it keeps its own style, e.g. one set
+    /// with `CtxRef::set_style`.
+    #[cfg(feature = "theme")]
+    pub fn without_theme(mut self) -> Self {
+        self.without_theme = true;
+        self
+    }
+
+    /// Restores egui's memory, e.g. window positions, from a file and saves it there again.
+    #[cfg(feature = "persistence")]
+    pub fn with_persistence(mut self, persistence: EguiPersistence) -> Self {
+        self.persistence = Some(persistence);
+        self
+    }
+}
+
+/// Adds an already built system to a `DispatcherBuilder`.
+struct BuiltSystem(Box<dyn ParallelRunnable>);
+
+impl System for BuiltSystem {
+    fn build(self) -> Box<dyn ParallelRunnable + 'static> {
+        self.0
+    }
+}
 
-impl SystemBundle for EguiBundle {
+impl<K: EguiKey> SystemBundle for EguiBundle<K> {
     fn load(
         &mut self,
         _world: &mut World,
         resources: &mut Resources,
         builder: &mut DispatcherBuilder,
     ) -> Result<(), Error> {
-        resources.insert(EguiInputGrab::default());
-        resources.insert(EguiConfig::default());
-        resources.insert(EguiContext::default());
+        resources.insert(EguiInputGrab::<K>::default());
+        let mut egui_ctx = EguiContext::<K>::default();
+        if let Some(console) = &self.console {
+            egui_ctx.hotkeys.push(console.hotkey());
+        }
+        if let Some(offscreen_size) = self.offscreen_size {
+            egui_ctx.offscreen_size = Some(offscreen_size);
+            egui_ctx.input_routing.pointer = false;
+            egui_ctx.input_routing.cursor_icon = false;
+        }
+        #[cfg(feature = "persistence")]
+        let persistence = self.persistence.take().map(|persistence| {
+            if let Some(memory) = persistence.load() {
+                *egui_ctx.ctx.memory() = memory;
+            }
+            PersistenceSystem::<K>::new(persistence, egui_ctx.ctx.clone())
+        });
+        resources.insert(egui_ctx);
+        resources.insert(EguiRenderStats::<K>::default());
+        // Resources shared by all contexts.
+        if let Some(config) = self.config.take() {
+            resources.insert(config);
+        }
+        else if !resources.contains::<EguiConfig>() {
+            resources.insert(EguiConfig::default());
+        }
+        if !resources.contains::<EguiClipboard>() {
+            resources.insert(EguiClipboard::default());
+        }
+        if !resources.contains::<EguiSpeech>() {
+            resources.insert(EguiSpeech::default());
+        }
+        if let Some(fonts) = self.fonts.take() {
+            resources.insert(fonts);
+        }
+        else if !resources.contains::<EguiFonts>() {
+            resources.insert(EguiFonts::default());
+        }
+        #[cfg(feature = "theme")]
+        if let Some(theme) = self.theme.take() {
+            resources.insert(theme);
+        }
+        else if !resources.contains::<EguiTheme>() {
+            resources.insert(EguiTheme::default());
+        }
+        if self.console.is_some() && !resources.contains::<ConsoleCommands>() {
+            resources.insert(ConsoleCommands::default());
+        }
+        // Shown by `RenderEgui`, see `ResourceInspectorWindow`.
+        if let Some(inspector) = self.resource_inspector.take() {
+            resources.insert(ResourceInspectorWindow::<K>::new(inspector));
+        }
 
         /*let mut window_events = resources
             .get_mut::<EventChannel<WindowEvent<'static>>>()
             .expect("EventChannel<WindowEvent>> missing");
         let window_event_reader = window_events.register_reader();*/
 
-        let winit_event_reader = resources
+        let mut winit_events = resources
             .get_mut::<EventChannel<Event<'_, ()>>>()
-            .expect("Window event channel not found in resources")
-            .register_reader();
+            .expect("Window event channel not found in resources");
 
-        builder.add_system(EguiSystem::new(winit_event_reader));
+        // The theme and fonts are applied before the frame starts, so that they are used right
+        // away. The theme may change the text styles, so it comes first.
+        #[cfg(feature = "theme")]
+        if !self.without_theme {
+            builder.add_system(EguiThemeSystem::<K>::default());
+        }
+        builder.add_system(EguiFontSystem::<K>::default());
+        builder.add_system(EguiSystem::<K>::new(winit_events.register_reader()));
+        for system in self.ui_systems.drain(..) {
+            builder.add_system(BuiltSystem(system));
+        }
+        if let Some(inspector) = self.entity_inspector.take() {
+            builder.add_system(EntityInspectorSystem::<K>::new(inspector));
+        }
+        if let Some(console) = self.console.take() {
+            builder.add_system(ConsoleSystem::<K>::new(console));
+        }
+        if let Some(profiler) = self.profiler.take() {
+            builder.add_system(ProfilerSystem::<K>::new(profiler));
+        }
+        #[cfg(feature = "persistence")]
+        if let Some(persistence) = persistence {
+            builder.add_system(persistence);
+        }
 
         Ok(())
     }
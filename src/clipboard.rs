@@ -0,0 +1,121 @@
+use derivative::Derivative;
+
+/// Access to a clipboard that egui can copy text to and paste text from.
+///
+/// Insert an `EguiClipboard` with your own provider into the resources to replace the system
+/// clipboard, e.g. for headless tests or platforms without a clipboard.
+pub trait ClipboardProvider: Send + Sync + 'static {
+    /// Returns the current text contents of the clipboard, if any.
+    fn get_contents(&mut self) -> Option<String>;
+
+    /// Replaces the contents of the clipboard with `contents`.
+    fn set_contents(&mut self, contents: String);
+}
+
+/// Resource holding the `ClipboardProvider` used by `EguiSystem`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct EguiClipboard {
+    #[derivative(Debug = "ignore")]
+    provider: Box<dyn ClipboardProvider>,
+}
+
+impl EguiClipboard {
+    pub fn new(provider: impl ClipboardProvider) -> Self {
+        Self {
+            provider: Box::new(provider),
+        }
+    }
+
+    pub fn get_contents(&mut self) -> Option<String> {
+        self.provider.get_contents()
+    }
+
+    pub fn set_contents(&mut self, contents: String) {
+        self.provider.set_contents(contents)
+    }
+}
+
+impl Default for EguiClipboard {
+    /// Uses the system clipboard if the `clipboard` feature is enabled, and an in-memory clipboard
+    /// otherwise.
+    fn default() -> Self {
+        #[cfg(feature = "clipboard")]
+        let provider = SystemClipboard;
+        #[cfg(not(feature = "clipboard"))]
+        let provider = InMemoryClipboard::default();
+
+        Self::new(provider)
+    }
+}
+
+/// A clipboard that only lives in memory and isn't shared with other applications.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryClipboard {
+    pub contents: Option<String>,
+}
+
+impl ClipboardProvider for InMemoryClipboard {
+    fn get_contents(&mut self) -> Option<String> {
+        self.contents.clone()
+    }
+
+    fn set_contents(&mut self, contents: String) {
+        self.contents = Some(contents);
+    }
+}
+
+#[cfg(feature = "clipboard")]
+pub use self::system::SystemClipboard;
+
+#[cfg(feature = "clipboard")]
+mod system {
+    use std::cell::RefCell;
+
+    use ::clipboard::{
+        ClipboardContext,
+        ClipboardProvider as _,
+    };
+
+    use super::ClipboardProvider;
+
+    thread_local! {
+        // The clipboard context is not `Send` on every platform, and on X11 the copied text is
+        // only served as long as the context is alive. So we keep one per thread.
+        static CLIPBOARD: RefCell<Option<ClipboardContext>> = RefCell::new(None);
+    }
+
+    fn with_clipboard<R>(f: impl FnOnce(&mut ClipboardContext) -> R) -> Option<R> {
+        CLIPBOARD.with(|clipboard| {
+            let mut clipboard = clipboard.borrow_mut();
+            if clipboard.is_none() {
+                match ClipboardContext::new() {
+                    Ok(context) => *clipboard = Some(context),
+                    Err(e) => {
+                        log::error!("Failed to open clipboard: {}", e);
+                        return None;
+                    }
+                }
+            }
+            clipboard.as_mut().map(f)
+        })
+    }
+
+    /// The system clipboard.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct SystemClipboard;
+
+    impl ClipboardProvider for SystemClipboard {
+        fn get_contents(&mut self) -> Option<String> {
+            with_clipboard(|clipboard| clipboard.get_contents())?
+                .map_err(|e| log::error!("Failed to read from clipboard: {}", e))
+                .ok()
+        }
+
+        fn set_contents(&mut self, contents: String) {
+            if let Some(Err(e)) = with_clipboard(|clipboard| clipboard.set_contents(contents)) {
+                log::error!("Failed to write to clipboard: {}", e);
+            }
+        }
+    }
+}
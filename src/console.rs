@@ -0,0 +1,771 @@
+use std::{
+    collections::{
+        BTreeMap,
+        VecDeque,
+    },
+    fmt,
+    marker::PhantomData,
+    str::FromStr,
+    sync::{
+        Arc,
+        Mutex,
+        MutexGuard,
+    },
+};
+
+use amethyst_core::ecs::{
+    ParallelRunnable,
+    System,
+    SystemBuilder,
+};
+use amethyst_input::VirtualKeyCode;
+use derivative::Derivative;
+use egui::{
+    Align,
+    Align2,
+    Color32,
+    CtxRef,
+    Id,
+    Key,
+    Label,
+    ScrollArea,
+    TextEdit,
+    TextStyle,
+};
+use log::{
+    Level,
+    LevelFilter,
+    Log,
+    Metadata,
+    Record,
+    SetLoggerError,
+};
+use crate::system::{
+    EguiContext,
+    EguiInputGrab,
+    EguiKey,
+};
+
+/// A line in the console's scrollback.
+#[derive(Clone, Debug)]
+pub struct ConsoleLine {
+    /// The level of a log message, or `None` for commands and their output.
+    pub level: Option<Level>,
+    pub text: String,
+}
+
+#[derive(Debug)]
+struct ScrollbackInner {
+    lines: VecDeque<ConsoleLine>,
+    max_lines: usize,
+    /// Number of lines pushed so far, so that the console knows when to scroll down.
+    pushed: u64,
+}
+
+/// The console's scrollback, shared between the console and its `ConsoleLogger`.
+#[derive(Clone, Debug)]
+struct Scrollback(Arc<Mutex<ScrollbackInner>>);
+
+impl Scrollback {
+    fn new(max_lines: usize) -> Self {
+        Self(Arc::new(Mutex::new(ScrollbackInner {
+            lines: VecDeque::new(),
+            max_lines,
+            pushed: 0,
+        })))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ScrollbackInner> {
+        // A panic while holding the lock can't leave the scrollback in an invalid state.
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, level: Option<Level>, text: String) {
+        let mut inner = self.lock();
+        while inner.lines.len() >= inner.max_lines.max(1) {
+            inner.lines.pop_front();
+        }
+        inner.lines.push_back(ConsoleLine { level, text });
+        inner.pushed += 1;
+    }
+
+    fn clear(&self) {
+        self.lock().lines.clear();
+    }
+}
+
+/// A `log::Log` implementation that writes all records into the scrollback of an `EguiConsole`,
+/// and optionally forwards them to another logger.
+///
+/// Create it with `EguiConsole::logger`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct ConsoleLogger {
+    scrollback: Scrollback,
+    #[derivative(Debug = "ignore")]
+    inner: Option<Box<dyn Log>>,
+}
+
+impl ConsoleLogger {
+    /// Forwards all records to `inner` too, e.g. a `fern::Dispatch` that logs to stdout.
+    pub fn with_inner(mut self, inner: Box<dyn Log>) -> Self {
+        self.inner = Some(inner);
+        self
+    }
+
+    /// Installs this as the global logger. This fails if a logger is already installed, e.g. by
+    /// `amethyst::start_logger`.
+    pub fn install(self, max_level: LevelFilter) -> Result<(), SetLoggerError> {
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        if let Some(inner) = &self.inner {
+            if inner.enabled(record.metadata()) {
+                inner.log(record);
+            }
+        }
+
+        self.scrollback.push(
+            Some(record.level()),
+            format!("[{}] {}", record.target(), record.args()),
+        );
+    }
+
+    fn flush(&self) {
+        if let Some(inner) = &self.inner {
+            inner.flush();
+        }
+    }
+}
+
+/// The arguments of a console command.
+///
+/// Arguments are separated by whitespace. Use double quotes for arguments containing whitespace.
+#[derive(Clone, Debug, Default)]
+pub struct ConsoleArgs {
+    args: Vec<String>,
+}
+
+impl ConsoleArgs {
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    /// Returns the argument at `index`.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.args.get(index).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.args.iter().map(String::as_str)
+    }
+
+    /// Parses the argument at `index`. Returns an error message for the console if the argument
+    /// is missing or invalid.
+    pub fn parse<T>(&self, index: usize) -> Result<T, String>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let arg = self
+            .get(index)
+            .ok_or_else(|| format!("Missing argument {}", index + 1))?;
+        arg.parse()
+            .map_err(|e| format!("Invalid argument {} '{}': {}", index + 1, arg, e))
+    }
+
+    /// Parses the argument at `index`, or returns `default` if it is missing.
+    pub fn parse_or<T>(&self, index: usize, default: T) -> Result<T, String>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        if index < self.len() {
+            self.parse(index)
+        }
+        else {
+            Ok(default)
+        }
+    }
+}
+
+/// Splits a command line into words. Double quotes group words, and `\"` is a literal quote.
+fn split_command_line(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if quoted => {
+                if let Some(c) = chars.next() {
+                    word.push(c);
+                }
+            }
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+
+    words
+}
+
+type CommandHandler = Box<dyn FnMut(&ConsoleArgs) -> Result<String, String> + Send + Sync>;
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct ConsoleCommand {
+    help: String,
+    #[derivative(Debug = "ignore")]
+    handler: CommandHandler,
+}
+
+/// Resource with the commands that can be run from the console.
+///
+/// `help` lists all commands and `clear` clears the scrollback.
+#[derive(Debug, Default)]
+pub struct ConsoleCommands {
+    commands: BTreeMap<String, ConsoleCommand>,
+}
+
+impl ConsoleCommands {
+    /// Registers a command, replacing any command with the same name. The handler returns the
+    /// output to show in the console, or an error message.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        help: impl Into<String>,
+        handler: impl FnMut(&ConsoleArgs) -> Result<String, String> + Send + Sync + 'static,
+    ) {
+        self.commands.insert(
+            name.into(),
+            ConsoleCommand {
+                help: help.into(),
+                handler: Box::new(handler),
+            },
+        );
+    }
+
+    /// Removes a command. Returns whether it was registered.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.commands.remove(name).is_some()
+    }
+
+    /// Iterates over the names of all commands in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(String::as_str)
+    }
+
+    /// Returns the help text of a command.
+    pub fn help(&self, name: &str) -> Option<&str> {
+        self.commands.get(name).map(|command| command.help.as_str())
+    }
+
+    /// Parses `line` and runs the command.
+    pub fn run(&mut self, line: &str) -> Result<String, String> {
+        let mut words = split_command_line(line);
+        if words.is_empty() {
+            return Ok(String::new());
+        }
+        let name = words.remove(0);
+        let args = ConsoleArgs { args: words };
+
+        if name == "help" {
+            return Ok(self.help_text(args.get(0)));
+        }
+
+        match self.commands.get_mut(&name) {
+            Some(command) => (command.handler)(&args),
+            None => Err(format!("Unknown command '{}'. Type 'help' for a list.", name)),
+        }
+    }
+
+    fn help_text(&self, name: Option<&str>) -> String {
+        match name {
+            Some(name) => {
+                self.help(name)
+                    .map(|help| format!("{}: {}", name, help))
+                    .unwrap_or_else(|| format!("Unknown command '{}'", name))
+            }
+            None => {
+                let mut text =
+                    String::from("help [command]: Lists commands\nclear: Clears the console");
+                for (name, command) in &self.commands {
+                    text.push('\n');
+                    text.push_str(name);
+                    text.push_str(": ");
+                    text.push_str(&command.help);
+                }
+                text
+            }
+        }
+    }
+}
+
+/// A drop-down developer console. Add it with `EguiBundle::with_console`.
+///
+/// To show log messages in the console, install its logger, e.g.
+/// `console.logger().install(LevelFilter::Debug)`. If another logger is already installed, which
+/// is the case with `amethyst::start_logger`, build that logger yourself and pass it to
+/// `ConsoleLogger::with_inner` instead.
+#[derive(Clone, Debug)]
+pub struct EguiConsole {
+    toggle_key: VirtualKeyCode,
+    scrollback: Scrollback,
+}
+
+impl Default for EguiConsole {
+    fn default() -> Self {
+        Self {
+            toggle_key: VirtualKeyCode::Grave,
+            scrollback: Scrollback::new(1000),
+        }
+    }
+}
+
+impl EguiConsole {
+    /// Sets the key that opens and closes the console. Defaults to the key left of `1`.
+    ///
+    /// The key doesn't toggle the console while another text edit has the keyboard focus, so
+    /// that it can still be typed there.
+    pub fn with_toggle_key(mut self, key: VirtualKeyCode) -> Self {
+        self.toggle_key = key;
+        self
+    }
+
+    /// Sets how many lines the scrollback keeps. Defaults to 1000.
+    pub fn with_max_lines(self, max_lines: usize) -> Self {
+        self.scrollback.lock().max_lines = max_lines;
+        self
+    }
+
+    /// Returns the toggle key and the console's input, which has it. See `EguiContext::hotkeys`.
+    pub(crate) fn hotkey(&self) -> (VirtualKeyCode, Id) {
+        (self.toggle_key, console_input_id())
+    }
+
+    /// Returns a logger that writes into this console's scrollback.
+    pub fn logger(&self) -> ConsoleLogger {
+        ConsoleLogger {
+            scrollback: self.scrollback.clone(),
+            inner: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ConsoleState {
+    open: bool,
+    input: String,
+    history: Vec<String>,
+    /// Position in `history` while browsing it with the arrow keys.
+    history_index: Option<usize>,
+    focus_input: bool,
+    /// Lines pushed to the scrollback when it was last shown.
+    seen_lines: u64,
+}
+
+impl ConsoleState {
+    fn show(&mut self, ctx: &CtxRef, scrollback: &Scrollback, commands: &mut ConsoleCommands) {
+        let width = ctx.available_rect().width();
+
+        egui::Window::new("Console")
+            .anchor(Align2::CENTER_TOP, [0.0, 0.0])
+            .default_width(width)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ScrollArea::from_max_height(300.0).show(ui, |ui| {
+                    let inner = scrollback.lock();
+                    for line in &inner.lines {
+                        let label = Label::new(&line.text).monospace();
+                        let label = match line.level {
+                            Some(Level::Error) => label.text_color(Color32::RED),
+                            Some(Level::Warn) => label.text_color(Color32::YELLOW),
+                            Some(Level::Debug) | Some(Level::Trace) => {
+                                label.text_color(Color32::GRAY)
+                            }
+                            Some(Level::Info) | None => label,
+                        };
+                        ui.add(label);
+                    }
+                    if inner.pushed != self.seen_lines {
+                        self.seen_lines = inner.pushed;
+                        ui.scroll_to_cursor(Align::BOTTOM);
+                    }
+                });
+
+                ui.separator();
+
+                let response = ui.add(
+                    TextEdit::singleline(&mut self.input)
+                        .id(console_input_id())
+                        .text_style(TextStyle::Monospace)
+                        .desired_width(f32::INFINITY),
+                );
+
+                if self.focus_input {
+                    response.request_focus();
+                    self.focus_input = false;
+                }
+
+                let input = ui.input();
+                if response.lost_focus() && input.key_pressed(Key::Enter) {
+                    self.submit(scrollback, commands);
+                    self.focus_input = true;
+                }
+                else if response.has_focus() || response.lost_focus() {
+                    if input.key_pressed(Key::ArrowUp) {
+                        self.browse_history(true);
+                    }
+                    else if input.key_pressed(Key::ArrowDown) {
+                        self.browse_history(false);
+                    }
+                    else if input.key_pressed(Key::Tab) {
+                        self.complete(scrollback, commands);
+                        // Tab would move the focus to the next widget otherwise.
+                        self.focus_input = true;
+                    }
+                }
+            });
+    }
+
+    fn submit(&mut self, scrollback: &Scrollback, commands: &mut ConsoleCommands) {
+        let line = std::mem::take(&mut self.input).trim().to_owned();
+        self.history_index = None;
+        if line.is_empty() {
+            return;
+        }
+
+        scrollback.push(None, format!("> {}", line));
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+
+        if line == "clear" {
+            scrollback.clear();
+            return;
+        }
+
+        // The scrollback must not be locked while the command runs, since it might log.
+        match commands.run(&line) {
+            Ok(output) => {
+                if !output.is_empty() {
+                    scrollback.push(None, output);
+                }
+            }
+            Err(e) => scrollback.push(Some(Level::Error), e),
+        }
+    }
+
+    fn browse_history(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+
+        self.history_index = match (self.history_index, older) {
+            (None, true) => Some(self.history.len() - 1),
+            (None, false) => None,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i + 1 < self.history.len() => Some(i + 1),
+            (Some(_), false) => None,
+        };
+
+        self.input = self
+            .history_index
+            .map(|i| self.history[i].clone())
+            .unwrap_or_default();
+    }
+
+    /// Completes the command name. If several commands match, they are listed in the scrollback
+    /// and the input is completed to their common prefix.
+    fn complete(&mut self, scrollback: &Scrollback, commands: &ConsoleCommands) {
+        let prefix = self.input.trim_start();
+        if prefix.contains(char::is_whitespace) {
+            return;
+        }
+
+        let candidates = commands
+            .names()
+            .chain(vec!["clear", "help"])
+            .filter(|name| name.starts_with(prefix))
+            .collect::<Vec<_>>();
+
+        match candidates.as_slice() {
+            [] => {}
+            [name] => self.input = format!("{} ", name),
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(first.len(), |len, name| {
+                    first
+                        .chars()
+                        .zip(name.chars())
+                        .take_while(|(a, b)| a == b)
+                        .map(|(a, _)| a.len_utf8())
+                        .sum::<usize>()
+                        .min(len)
+                });
+                self.input = first[..common].to_owned();
+                scrollback.push(None, candidates.join("  "));
+            }
+        }
+    }
+}
+
+fn console_input_id() -> Id {
+    Id::new("amethyst_egui::console::input")
+}
+
+/// Shows an `EguiConsole` with the egui context `K`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub(crate) struct ConsoleSystem<K> {
+    console: EguiConsole,
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<K>,
+}
+
+impl<K> ConsoleSystem<K> {
+    pub fn new(console: EguiConsole) -> Self {
+        Self {
+            console,
+            _key: PhantomData,
+        }
+    }
+}
+
+impl<K: EguiKey> System for ConsoleSystem<K> {
+    fn build(self) -> Box<dyn ParallelRunnable + 'static> {
+        let mut state = ConsoleState::default();
+
+        Box::new(
+            SystemBuilder::new(format!("ConsoleSystem<{}>", std::any::type_name::<K>()))
+                .read_resource::<EguiContext<K>>()
+                .write_resource::<EguiInputGrab<K>>()
+                .write_resource::<ConsoleCommands>()
+                .build(move |_commands, _world, resources, _queries| {
+                    let (egui_ctx, input_grab, commands) = resources;
+
+                    // `EguiSystem` keeps the toggle key from egui, see `EguiConsole::hotkey`.
+                    for key in &egui_ctx.pressed_hotkeys {
+                        if *key == self.console.toggle_key {
+                            state.open = !state.open;
+                            state.focus_input = state.open;
+                        }
+                    }
+
+                    if !state.open {
+                        return;
+                    }
+
+                    // Gameplay shouldn't react to typing into the console.
+                    input_grab.keyboard = true;
+
+                    egui_ctx.with_ui(|ctx| {
+                        state.show(ctx, &self.console.scrollback, commands);
+                    });
+                }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(names: &[&str]) -> ConsoleCommands {
+        let mut commands = ConsoleCommands::default();
+        for name in names {
+            commands.register(*name, "", |_| Ok(String::new()));
+        }
+        commands
+    }
+
+    fn lines(scrollback: &Scrollback) -> Vec<String> {
+        scrollback
+            .lock()
+            .lines
+            .iter()
+            .map(|line| line.text.clone())
+            .collect()
+    }
+
+    fn history(entries: &[&str]) -> ConsoleState {
+        ConsoleState {
+            history: entries.iter().map(|entry| entry.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn split_words() {
+        assert_eq!(
+            split_command_line("spawn  orc\t3 "),
+            vec!["spawn", "orc", "3"]
+        );
+    }
+
+    #[test]
+    fn split_empty_input() {
+        assert!(split_command_line("").is_empty());
+        assert!(split_command_line("   ").is_empty());
+    }
+
+    #[test]
+    fn split_quoted_words() {
+        assert_eq!(
+            split_command_line(r#"say "hello world" a"b c"d"#),
+            vec!["say", "hello world", "ab cd"]
+        );
+        // An empty pair of quotes is an empty word.
+        assert_eq!(
+            split_command_line(r#"set name """#),
+            vec!["set", "name", ""]
+        );
+        // An unterminated quote extends to the end of the line.
+        assert_eq!(
+            split_command_line(r#"say "hello  "#),
+            vec!["say", "hello  "]
+        );
+    }
+
+    #[test]
+    fn split_escapes() {
+        assert_eq!(
+            split_command_line(r#"say "a \"quoted\" word" "back\\slash""#),
+            vec!["say", r#"a "quoted" word"#, r"back\slash"]
+        );
+        // Backslashes are only escapes inside quotes.
+        assert_eq!(split_command_line(r"cd C:\games"), vec!["cd", r"C:\games"]);
+    }
+
+    #[test]
+    fn complete_without_match() {
+        let scrollback = Scrollback::new(10);
+        let mut state = ConsoleState {
+            input: "xyz".to_owned(),
+            ..Default::default()
+        };
+        state.complete(&scrollback, &commands(&["spawn", "speed"]));
+
+        assert_eq!(state.input, "xyz");
+        assert!(lines(&scrollback).is_empty());
+    }
+
+    #[test]
+    fn complete_single_match() {
+        let scrollback = Scrollback::new(10);
+        let mut state = ConsoleState {
+            input: "spa".to_owned(),
+            ..Default::default()
+        };
+        state.complete(&scrollback, &commands(&["spawn", "speed"]));
+
+        assert_eq!(state.input, "spawn ");
+        assert!(lines(&scrollback).is_empty());
+    }
+
+    #[test]
+    fn complete_many_matches() {
+        let scrollback = Scrollback::new(10);
+        let mut state = ConsoleState {
+            input: "s".to_owned(),
+            ..Default::default()
+        };
+        state.complete(&scrollback, &commands(&["spawn", "spawn_wave", "teleport"]));
+
+        assert_eq!(state.input, "spawn");
+        assert_eq!(lines(&scrollback), vec!["spawn  spawn_wave"]);
+    }
+
+    #[test]
+    fn complete_only_command_names() {
+        let scrollback = Scrollback::new(10);
+        let mut state = ConsoleState {
+            input: "help spa".to_owned(),
+            ..Default::default()
+        };
+        state.complete(&scrollback, &commands(&["spawn"]));
+
+        assert_eq!(state.input, "help spa");
+    }
+
+    #[test]
+    fn browse_empty_history() {
+        let mut state = history(&[]);
+        state.input = "typed".to_owned();
+        state.browse_history(true);
+
+        assert_eq!(state.input, "typed");
+        assert_eq!(state.history_index, None);
+    }
+
+    #[test]
+    fn browse_history_stops_at_oldest_entry() {
+        let mut state = history(&["first", "second"]);
+
+        state.browse_history(true);
+        assert_eq!(state.input, "second");
+        state.browse_history(true);
+        assert_eq!(state.input, "first");
+        state.browse_history(true);
+        assert_eq!(state.input, "first");
+        assert_eq!(state.history_index, Some(0));
+    }
+
+    #[test]
+    fn browse_history_past_newest_entry_clears_input() {
+        let mut state = history(&["first", "second"]);
+
+        state.browse_history(true);
+        state.browse_history(true);
+        state.browse_history(false);
+        assert_eq!(state.input, "second");
+        state.browse_history(false);
+        assert_eq!(state.input, "");
+        assert_eq!(state.history_index, None);
+        state.browse_history(false);
+        assert_eq!(state.input, "");
+    }
+
+    #[test]
+    fn submit_skips_repeated_history_entries() {
+        let scrollback = Scrollback::new(10);
+        let mut commands = commands(&["spawn"]);
+        let mut state = ConsoleState::default();
+        for line in &["spawn", "spawn", "  ", "help"] {
+            state.input = line.to_string();
+            state.submit(&scrollback, &mut commands);
+        }
+
+        assert_eq!(state.history, vec!["spawn", "help"]);
+    }
+}
@@ -0,0 +1,366 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    marker::PhantomData,
+};
+
+use amethyst_assets::{
+    register_asset_type,
+    register_importer,
+    Asset,
+    AssetProcessorSystem,
+    AssetStorage,
+    DefaultLoader,
+    Format,
+    Handle,
+    Loader,
+    ProcessableAsset,
+    ProcessingState,
+};
+use amethyst_core::ecs::{
+    ParallelRunnable,
+    System,
+    SystemBuilder,
+};
+use amethyst_error::Error;
+use derivative::Derivative;
+use egui::{
+    FontDefinitions,
+    FontFamily,
+    TextStyle,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use type_uuid::TypeUuid;
+
+use crate::system::{
+    EguiContext,
+    EguiKey,
+};
+
+/// The contents of a TrueType or OpenType font file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "2c4f1d3e-7a0b-4e5c-9b61-3f8d2a6c5e17"]
+pub struct EguiFontData(pub Vec<u8>);
+
+/// A font that can be used by egui. Add it to the `EguiFonts` resource.
+#[derive(Clone, Debug, TypeUuid)]
+#[uuid = "8e93b6a1-5d2f-4c07-a4e8-1b7c9f0d3a52"]
+pub struct EguiFont {
+    data: Cow<'static, [u8]>,
+}
+
+impl Asset for EguiFont {
+    type Data = EguiFontData;
+
+    fn name() -> &'static str {
+        "egui::Font"
+    }
+}
+
+impl ProcessableAsset for EguiFont {
+    fn process(data: EguiFontData) -> Result<ProcessingState<EguiFontData, EguiFont>, Error> {
+        Ok(ProcessingState::Loaded(EguiFont {
+            data: Cow::Owned(data.0),
+        }))
+    }
+}
+
+register_asset_type!(EguiFontData => EguiFont; AssetProcessorSystem<EguiFont>);
+
+/// Imports `.otf` files and TrueType collections (`.ttc`) as `EguiFontData`, and `.ttf` files if
+/// the `ttf` feature is enabled. Only the first font of a collection is used.
+///
+/// The `ttf` feature is off by default, because `amethyst_ui` also registers an importer for
+/// `.ttf` and an extension can only have one importer. Without it, add `.ttf` files with
+/// `EguiFonts::with_font_bytes`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "d7a0e4c2-96b3-4f1e-8c5a-2e6f0b9d7c34"]
+pub struct OtfFormat;
+
+register_importer!(".otf", OtfFormat);
+register_importer!(".ttc", OtfFormat);
+#[cfg(feature = "ttf")]
+register_importer!(".ttf", OtfFormat);
+
+impl Format<EguiFontData> for OtfFormat {
+    fn name(&self) -> &'static str {
+        "OTF"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<EguiFontData, Error> {
+        Ok(EguiFontData(bytes))
+    }
+}
+
+/// Where a font is placed in the fallback list of its family.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FontPriority {
+    /// Used before the other fonts of the family, e.g. to replace the default font.
+    Highest,
+    /// Only used for glyphs that the other fonts of the family lack, e.g. CJK or icons.
+    Lowest,
+}
+
+#[derive(Clone, Debug)]
+enum FontSource {
+    Path(String),
+    Handle(Handle<EguiFont>),
+    Bytes(Cow<'static, [u8]>),
+}
+
+#[derive(Clone, Debug)]
+struct FontEntry {
+    name: String,
+    source: FontSource,
+    family: FontFamily,
+    priority: FontPriority,
+}
+
+/// Resource describing the fonts of all egui contexts.
+///
+/// The fonts are applied to the contexts as soon as they are loaded, and again whenever a font is
+/// reloaded or the resource is changed.
+#[derive(Clone, Debug)]
+pub struct EguiFonts {
+    definitions: FontDefinitions,
+    fonts: Vec<FontEntry>,
+    /// Text styles of the theme, which override those of `definitions`.
+    theme_text_styles: BTreeMap<TextStyle, (FontFamily, f32)>,
+    /// Incremented on every change, so that the contexts know when to update their fonts.
+    generation: u64,
+}
+
+impl Default for EguiFonts {
+    /// Egui's built-in fonts. They are only applied once they are changed, since the contexts
+    /// already use them.
+    fn default() -> Self {
+        Self {
+            generation: 0,
+            ..Self::new(FontDefinitions::default())
+        }
+    }
+}
+
+impl EguiFonts {
+    /// Creates fonts based on `definitions`, which contains egui's built-in fonts by default.
+    pub fn new(definitions: FontDefinitions) -> Self {
+        Self {
+            definitions,
+            fonts: Vec::new(),
+            theme_text_styles: BTreeMap::new(),
+            generation: 1,
+        }
+    }
+
+    /// Adds the font file at `path` to `family` with the name `name`. The file is loaded with the
+    /// `DefaultLoader`, see `OtfFormat` for the supported formats.
+    pub fn with_font(
+        mut self,
+        name: impl Into<String>,
+        path: impl Into<String>,
+        family: FontFamily,
+        priority: FontPriority,
+    ) -> Self {
+        self.add_font(name, path, family, priority);
+        self
+    }
+
+    /// Adds the font file at `path` to `family` with the name `name`. The file is loaded with the
+    /// `DefaultLoader`, see `OtfFormat` for the supported formats.
+    pub fn add_font(
+        &mut self,
+        name: impl Into<String>,
+        path: impl Into<String>,
+        family: FontFamily,
+        priority: FontPriority,
+    ) {
+        self.push(name.into(), FontSource::Path(path.into()), family, priority);
+    }
+
+    /// Adds the font file contents `data` to `family` with the name `name`, e.g. a `.ttf` file
+    /// read with `std::fs::read` or embedded with `include_bytes!`.
+    pub fn with_font_bytes(
+        mut self,
+        name: impl Into<String>,
+        data: impl Into<Cow<'static, [u8]>>,
+        family: FontFamily,
+        priority: FontPriority,
+    ) -> Self {
+        self.add_font_bytes(name, data, family, priority);
+        self
+    }
+
+    /// Adds the font file contents `data` to `family` with the name `name`, e.g. a `.ttf` file
+    /// read with `std::fs::read` or embedded with `include_bytes!`.
+    pub fn add_font_bytes(
+        &mut self,
+        name: impl Into<String>,
+        data: impl Into<Cow<'static, [u8]>>,
+        family: FontFamily,
+        priority: FontPriority,
+    ) {
+        self.push(name.into(), FontSource::Bytes(data.into()), family, priority);
+    }
+
+    /// Adds an already loaded font to `family` with the name `name`.
+    pub fn add_font_handle(
+        &mut self,
+        name: impl Into<String>,
+        handle: Handle<EguiFont>,
+        family: FontFamily,
+        priority: FontPriority,
+    ) {
+        self.push(name.into(), FontSource::Handle(handle), family, priority);
+    }
+
+    /// Removes all fonts with the name `name`.
+    pub fn remove_font(&mut self, name: &str) {
+        self.fonts.retain(|font| font.name != name);
+        self.generation += 1;
+    }
+
+    /// Returns the font definitions the fonts are added to.
+    pub fn definitions(&self) -> &FontDefinitions {
+        &self.definitions
+    }
+
+    /// Returns the font definitions the fonts are added to, e.g. to change the text sizes.
+    pub fn definitions_mut(&mut self) -> &mut FontDefinitions {
+        self.generation += 1;
+        &mut self.definitions
+    }
+
+    /// Replaces the text styles set by the theme. They are applied on top of `definitions`, so that
+    /// the sizes of a previous theme don't stick.
+    #[cfg(feature = "theme")]
+    pub(crate) fn set_theme_text_styles(
+        &mut self,
+        text_styles: BTreeMap<TextStyle, (FontFamily, f32)>,
+    ) {
+        if self.theme_text_styles != text_styles {
+            self.theme_text_styles = text_styles;
+            self.generation += 1;
+        }
+    }
+
+    fn push(
+        &mut self,
+        name: String,
+        source: FontSource,
+        family: FontFamily,
+        priority: FontPriority,
+    ) {
+        self.fonts.push(FontEntry {
+            name,
+            source,
+            family,
+            priority,
+        });
+        self.generation += 1;
+    }
+
+    /// Starts loading the fonts that were added by path.
+    fn load(&mut self, loader: &DefaultLoader) {
+        for font in &mut self.fonts {
+            if let FontSource::Path(path) = &font.source {
+                font.source = FontSource::Handle(loader.load(path));
+            }
+        }
+    }
+
+    /// Returns the versions of the loaded fonts, so that reloads can be detected.
+    fn versions(&self, storage: &AssetStorage<EguiFont>) -> Vec<Option<u32>> {
+        self.fonts
+            .iter()
+            .map(|font| {
+                match &font.source {
+                    FontSource::Handle(handle) => {
+                        storage
+                            .get_asset_with_version(handle)
+                            .map(|(_, version)| version)
+                    }
+                    FontSource::Path(_) | FontSource::Bytes(_) => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Builds the font definitions with all loaded fonts.
+    fn build(&self, storage: &AssetStorage<EguiFont>) -> FontDefinitions {
+        let mut definitions = self.definitions.clone();
+        definitions
+            .family_and_size
+            .extend(self.theme_text_styles.clone());
+
+        for font in &self.fonts {
+            let data = match &font.source {
+                FontSource::Handle(handle) => storage.get(handle).map(|asset| &asset.data),
+                FontSource::Path(_) => None,
+                FontSource::Bytes(data) => Some(data),
+            };
+            let data = match data {
+                Some(data) => data,
+                None => continue,
+            };
+
+            definitions.font_data.insert(font.name.clone(), data.clone());
+            let family = definitions
+                .fonts_for_family
+                .entry(font.family)
+                .or_default();
+            match font.priority {
+                FontPriority::Highest => family.insert(0, font.name.clone()),
+                FontPriority::Lowest => family.push(font.name.clone()),
+            }
+        }
+
+        definitions
+    }
+}
+
+/// Applies `EguiFonts` to the egui context `K`.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub(crate) struct EguiFontSystem<K> {
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<K>,
+}
+
+impl<K: EguiKey> System for EguiFontSystem<K> {
+    fn build(self) -> Box<dyn ParallelRunnable + 'static> {
+        // Generation and font versions last applied to the context. The contexts start with the
+        // fonts of `EguiFonts::default`, so they don't need to be set again, which would rebuild
+        // the font atlas and replace fonts set directly on the context.
+        let mut applied: Option<(u64, Vec<Option<u32>>)> = Some((0, Vec::new()));
+
+        Box::new(
+            SystemBuilder::new(format!("EguiFontSystem<{}>", std::any::type_name::<K>()))
+                .read_resource::<DefaultLoader>()
+                .read_resource::<AssetStorage<EguiFont>>()
+                .write_resource::<EguiFonts>()
+                .read_resource::<EguiContext<K>>()
+                .build(move |_commands, _world, resources, _queries| {
+                    let (loader, storage, fonts, egui_ctx) = resources;
+
+                    fonts.load(loader);
+
+                    let versions = fonts.versions(storage);
+                    let up_to_date = applied
+                        .as_ref()
+                        .map_or(false, |(generation, applied_versions)| {
+                            *generation == fonts.generation && *applied_versions == versions
+                        });
+
+                    if !up_to_date {
+                        log::debug!("Applying fonts: versions={:?}", versions);
+                        // Takes effect at the start of the next frame.
+                        egui_ctx.ctx.set_fonts(fonts.build(storage));
+                        applied = Some((fonts.generation, versions));
+                    }
+                }),
+        )
+    }
+}
@@ -0,0 +1,437 @@
+use std::marker::PhantomData;
+
+use amethyst_core::{
+    ecs::{
+        world::SubWorld,
+        Entity,
+        EntityStore,
+        IntoQuery,
+        ParallelRunnable,
+        Resources,
+        System,
+        SystemBuilder,
+    },
+    math::Vector3,
+    transform::Transform,
+    Named,
+    Time,
+};
+use amethyst_window::ScreenDimensions;
+use derivative::Derivative;
+use egui::{
+    CollapsingHeader,
+    CtxRef,
+    DragValue,
+    ScrollArea,
+    Slider,
+    Ui,
+};
+
+use crate::system::{
+    EguiConfig,
+    EguiContext,
+    EguiKey,
+    MAX_UI_SCALE,
+    MIN_UI_SCALE,
+};
+
+/// Types that can be shown and edited in an inspector.
+pub trait Inspectable {
+    fn inspect(&mut self, ui: &mut Ui);
+}
+
+impl Inspectable for Transform {
+    fn inspect(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Translation");
+            vector3_ui(ui, self.translation_mut(), 0.1);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Rotation");
+            let (x, y, z) = self.euler_angles();
+            let mut angles = Vector3::new(x.to_degrees(), y.to_degrees(), z.to_degrees());
+            if vector3_ui(ui, &mut angles, 1.0) {
+                self.set_rotation_euler(
+                    angles.x.to_radians(),
+                    angles.y.to_radians(),
+                    angles.z.to_radians(),
+                );
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Scale");
+            vector3_ui(ui, self.scale_mut(), 0.01);
+        });
+    }
+}
+
+impl Inspectable for Named {
+    fn inspect(&mut self, ui: &mut Ui) {
+        let mut name = self.name.to_string();
+        if ui.text_edit_singleline(&mut name).changed() {
+            self.name = name.into();
+        }
+    }
+}
+
+impl Inspectable for Time {
+    fn inspect(&mut self, ui: &mut Ui) {
+        ui.label(format!("Frame: {}", self.frame_number()));
+        ui.label(format!("Delta: {:.4} s", self.delta_seconds()));
+        ui.label(format!("Real delta: {:.4} s", self.delta_real_seconds()));
+        ui.label(format!("Fixed delta: {:.4} s", self.fixed_seconds()));
+        ui.label(format!("Absolute time: {:.2} s", self.absolute_time_seconds()));
+        ui.label(format!(
+            "Absolute real time: {:.2} s",
+            self.absolute_real_time_seconds()
+        ));
+
+        let mut time_scale = self.time_scale();
+        if ui
+            .add(Slider::new(&mut time_scale, 0.0..=4.0).text("Time scale"))
+            .changed()
+        {
+            self.set_time_scale(time_scale);
+        }
+    }
+}
+
+impl Inspectable for ScreenDimensions {
+    fn inspect(&mut self, ui: &mut Ui) {
+        ui.label(format!("Size: {} x {}", self.width(), self.height()));
+        ui.label(format!("HiDPI factor: {}", self.hidpi_factor()));
+    }
+}
+
+impl Inspectable for EguiConfig {
+    fn inspect(&mut self, ui: &mut Ui) {
+        ui.add(Slider::new(&mut self.ui_scale, MIN_UI_SCALE..=MAX_UI_SCALE).text("UI scale"));
+        ui.add(Slider::new(&mut self.scroll_sensitivity, 0.1..=10.0).text("Scroll sensitivity"));
+        ui.add(
+            Slider::new(&mut self.scroll_points_per_line, 1.0..=200.0).text("Points per line"),
+        );
+        ui.checkbox(&mut self.invert_scroll_x, "Invert horizontal scrolling");
+        ui.checkbox(&mut self.invert_scroll_y, "Invert vertical scrolling");
+        ui.checkbox(&mut self.mirror_mouse_buttons, "Mirror mouse buttons");
+        #[cfg(feature = "webbrowser")]
+        ui.checkbox(&mut self.allow_webbrowser, "Open links in web browser");
+        ui.checkbox(&mut self.allow_clipboard, "Allow clipboard");
+        ui.checkbox(&mut self.enable_tts, "Text-to-speech");
+        ui.add(Slider::new(&mut self.tts_rate, 0.1..=4.0).text("Speech rate"));
+        ui.checkbox(&mut self.tts_interrupt, "Interrupt speech");
+    }
+}
+
+/// Shows drag values for the components of `vector`. Returns whether it was changed.
+pub(crate) fn vector3_ui(ui: &mut Ui, vector: &mut Vector3<f32>, speed: f32) -> bool {
+    let mut changed = false;
+    for value in vector.iter_mut() {
+        changed |= ui.add(DragValue::new(value).speed(speed)).changed();
+    }
+    changed
+}
+
+/// Type-erased access to one component type.
+trait ComponentInspector: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Declares the component access of the inspector system.
+    fn declare_access(&self, builder: SystemBuilder) -> SystemBuilder;
+
+    /// Shows the component of `entity`, if it has one.
+    fn inspect(&self, world: &mut SubWorld, entity: Entity, ui: &mut Ui);
+}
+
+struct TypedComponentInspector<T> {
+    name: &'static str,
+    _component: PhantomData<fn() -> T>,
+}
+
+impl<T> ComponentInspector for TypedComponentInspector<T>
+where
+    T: Inspectable + Send + Sync + 'static,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn declare_access(&self, builder: SystemBuilder) -> SystemBuilder {
+        builder.write_component::<T>()
+    }
+
+    fn inspect(&self, world: &mut SubWorld, entity: Entity, ui: &mut Ui) {
+        if let Ok(mut entry) = world.entry_mut(entity) {
+            if let Ok(component) = entry.get_component_mut::<T>() {
+                CollapsingHeader::new(self.name)
+                    .default_open(true)
+                    .show(ui, |ui| component.inspect(ui));
+            }
+        }
+    }
+}
+
+/// A window that lists all entities and lets you edit the components of the selected entity.
+///
+/// Only registered component types are shown. `Named` and `Transform` are registered by default.
+/// Add the inspector with `EguiBundle::with_entity_inspector`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct EguiEntityInspector {
+    #[derivative(Debug = "ignore")]
+    components: Vec<Box<dyn ComponentInspector>>,
+}
+
+impl Default for EguiEntityInspector {
+    fn default() -> Self {
+        Self::new()
+            .with_component::<Named>("Named")
+            .with_component::<Transform>("Transform")
+    }
+}
+
+impl EguiEntityInspector {
+    /// Creates an inspector without any registered component types.
+    pub fn new() -> Self {
+        Self {
+            components: Vec::new(),
+        }
+    }
+
+    /// Registers a component type, so that it is shown with `name` in the inspector.
+    pub fn with_component<T>(mut self, name: &'static str) -> Self
+    where
+        T: Inspectable + Send + Sync + 'static,
+    {
+        self.components.push(Box::new(TypedComponentInspector::<T> {
+            name,
+            _component: PhantomData,
+        }));
+        self
+    }
+}
+
+/// Returns the name of `entity`, if it has a `Named` component and `Named` is registered with the
+/// inspector.
+fn entity_name(world: &SubWorld, entity: Entity) -> Option<String> {
+    let entry = world.entry_ref(entity).ok()?;
+    let named = entry.get_component::<Named>().ok()?;
+    Some(named.name.to_string())
+}
+
+/// Shows the `EguiEntityInspector` with the egui context `K`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub(crate) struct EntityInspectorSystem<K> {
+    inspector: EguiEntityInspector,
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<K>,
+}
+
+impl<K> EntityInspectorSystem<K> {
+    pub fn new(inspector: EguiEntityInspector) -> Self {
+        Self {
+            inspector,
+            _key: PhantomData,
+        }
+    }
+}
+
+impl<K: EguiKey> System for EntityInspectorSystem<K> {
+    fn build(self) -> Box<dyn ParallelRunnable + 'static> {
+        let components = self.inspector.components;
+
+        let mut builder = SystemBuilder::new(format!(
+            "EntityInspectorSystem<{}>",
+            std::any::type_name::<K>()
+        ));
+        for component in &components {
+            builder = component.declare_access(builder);
+        }
+
+        let mut selected: Option<Entity> = None;
+
+        Box::new(
+            builder
+                .read_resource::<EguiContext<K>>()
+                .with_query(<Entity>::query())
+                .build(move |_commands, world, egui_ctx, query| {
+                    let entities = query.iter(world).copied().collect::<Vec<_>>();
+                    if selected.map_or(false, |entity| !entities.contains(&entity)) {
+                        selected = None;
+                    }
+
+                    egui_ctx.with_ui(|ctx| {
+                        egui::Window::new("Entities")
+                            .default_width(300.0)
+                            .show(ctx, |ui| {
+                                ScrollArea::from_max_height(200.0).show(ui, |ui| {
+                                    for entity in &entities {
+                                        let label = entity_name(world, *entity)
+                                            .unwrap_or_else(|| format!("{:?}", entity));
+                                        let is_selected = selected == Some(*entity);
+                                        if ui.selectable_label(is_selected, label).clicked() {
+                                            selected = Some(*entity);
+                                        }
+                                    }
+                                });
+
+                                ui.separator();
+
+                                if let Some(entity) = selected {
+                                    ui.heading(format!("{:?}", entity));
+                                    for component in &components {
+                                        ui.push_id(component.name(), |ui| {
+                                            component.inspect(world, entity, ui);
+                                        });
+                                    }
+                                }
+                                else {
+                                    ui.label("No entity selected");
+                                }
+                            });
+                    });
+                }),
+        )
+    }
+}
+
+/// Type-erased access to one resource type.
+trait ResourceInspector: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Shows the resource, or a note if it isn't in `resources`.
+    fn inspect(&self, resources: &Resources, ui: &mut Ui);
+}
+
+struct TypedResourceInspector<T> {
+    name: &'static str,
+    _resource: PhantomData<fn() -> T>,
+}
+
+impl<T> ResourceInspector for TypedResourceInspector<T>
+where
+    T: Inspectable + Send + Sync + 'static,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn inspect(&self, resources: &Resources, ui: &mut Ui) {
+        match resources.get_mut::<T>() {
+            Some(mut resource) => resource.inspect(ui),
+            None => {
+                ui.label("Not available");
+            }
+        }
+    }
+}
+
+/// A window that shows the registered resources and lets you edit them.
+///
+/// `Time`, `ScreenDimensions` and `EguiConfig` are registered by default. Add the inspector with
+/// `EguiBundle::with_resource_inspector`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct EguiResourceInspector {
+    #[derivative(Debug = "ignore")]
+    resources: Vec<Box<dyn ResourceInspector>>,
+}
+
+impl Default for EguiResourceInspector {
+    fn default() -> Self {
+        Self::new()
+            .with_resource::<Time>("Time")
+            .with_resource::<ScreenDimensions>("ScreenDimensions")
+            .with_resource::<EguiConfig>("EguiConfig")
+    }
+}
+
+impl EguiResourceInspector {
+    /// Creates an inspector without any registered resource types.
+    pub fn new() -> Self {
+        Self {
+            resources: Vec::new(),
+        }
+    }
+
+    /// Registers a resource type, so that it is shown with `name` in the inspector.
+    pub fn with_resource<T>(mut self, name: &'static str) -> Self
+    where
+        T: Inspectable + Send + Sync + 'static,
+    {
+        self.resources.push(Box::new(TypedResourceInspector::<T> {
+            name,
+            _resource: PhantomData,
+        }));
+        self
+    }
+
+    /// Shows the inspector window with `ctx`. No resource may be borrowed, as the editors borrow
+    /// the resources mutably.
+    fn show(&self, ctx: &CtxRef, resources: &Resources) {
+        egui::Window::new("Resources")
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                ScrollArea::auto_sized().show(ui, |ui| {
+                    for resource in &self.resources {
+                        CollapsingHeader::new(resource.name())
+                            .show(ui, |ui| resource.inspect(resources, ui));
+                    }
+                });
+            });
+    }
+}
+
+/// The `EguiResourceInspector` of the egui context `K`.
+///
+/// Resources can't be declared dynamically on a system, so the inspector is shown by a thread-local
+/// function that `RenderEgui<K>` adds in front of the render system. This doesn't depend on the
+/// order in which the bundles are added.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub(crate) struct ResourceInspectorWindow<K> {
+    inspector: EguiResourceInspector,
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<K: EguiKey> ResourceInspectorWindow<K> {
+    pub fn new(inspector: EguiResourceInspector) -> Self {
+        Self {
+            inspector,
+            _key: PhantomData,
+        }
+    }
+
+    /// Shows the inspector of the context `K`, if there is one.
+    ///
+    /// The window is taken out of the resources while it is shown, and the egui context is cloned,
+    /// so that all resources, including `EguiContext<K>`, can be edited without a double borrow.
+    /// The window itself is shown as not available.
+    pub fn show(resources: &mut Resources) {
+        let window = match resources.remove::<Self>() {
+            Some(window) => window,
+            None => return,
+        };
+
+        let ctx = resources
+            .get::<EguiContext<K>>()
+            .and_then(|egui_ctx| egui_ctx.with_ui(|ctx| ctx.clone()));
+        if let Some(ctx) = ctx {
+            window.inspector.show(&ctx, resources);
+        }
+
+        resources.insert(window);
+    }
+}
+
+/// Marks that the `ResourceInspectorWindow<K>` is already shown by a `RenderEgui<K>`, so that it
+/// isn't shown twice if the context is rendered to several targets.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub(crate) struct ResourceInspectorScheduled<K> {
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<fn() -> K>,
+}
@@ -14,12 +14,34 @@
 //!
 
 pub mod bundle;
+pub mod clipboard;
+pub mod console;
+pub mod fonts;
+pub mod inspector;
 pub mod pass;
+#[cfg(feature = "persistence")]
+pub mod persistence;
 pub mod plugin;
 pub mod pod;
+pub mod profiler;
+pub mod speech;
 pub mod system;
+#[cfg(feature = "theme")]
+pub mod theme;
+pub mod world;
 
 pub use bundle::EguiBundle;
-pub use system::{EguiConfig, EguiContext, EguiInputGrab};
+pub use crate::clipboard::{ClipboardProvider, EguiClipboard};
+pub use console::{ConsoleArgs, ConsoleCommands, ConsoleLogger, EguiConsole};
+pub use fonts::{EguiFont, EguiFonts, FontPriority};
+pub use inspector::{EguiEntityInspector, EguiResourceInspector, Inspectable};
+pub use speech::{EguiSpeech, SpeechBackend};
+pub use system::{DefaultEgui, EguiConfig, EguiContext, EguiInputGrab, EguiInputRouting, EguiKey};
+#[cfg(feature = "persistence")]
+pub use persistence::EguiPersistence;
 pub use plugin::RenderEgui;
+#[cfg(feature = "theme")]
+pub use theme::{EguiTheme, ThemePreset};
+pub use profiler::EguiProfiler;
+pub use world::EguiWorldPanel;
 pub use egui;
@@ -1,13 +1,34 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    marker::PhantomData,
+    ops::Range,
+    time::{
+        Duration,
+        Instant,
+    },
+};
 
 use amethyst_assets::{
+    AssetStorage,
     DefaultLoader,
     Handle,
     Loader,
     ProcessingQueue,
 };
+use amethyst_core::{
+    ecs::{
+        Entity,
+        IntoQuery,
+        Read,
+    },
+    math::Matrix4,
+    transform::Transform,
+};
 use amethyst_rendy::{
-    batch::OrderedOneLevelBatch,
+    camera::{
+        ActiveCamera,
+        Camera,
+    },
     pipeline::{
         PipelineDescBuilder,
         PipelinesBuilder,
@@ -17,6 +38,7 @@ use amethyst_rendy::{
             QueueId,
             RenderPassEncoder,
         },
+        factory::ImageState,
         graph::{
             render::{
                 PrepareResult,
@@ -31,23 +53,40 @@ use amethyst_rendy::{
                 Device,
                 ShaderError,
             },
-            image::Layout,
+            format::Aspects,
+            image::{
+                Access,
+                Extent,
+                Layout,
+                Offset,
+                SubresourceLayers,
+            },
             pass::Subpass,
             pso::{
+                BakedStates,
                 BlendState,
                 ColorBlendDesc,
                 ColorMask,
+                Comparison,
                 CreationError,
                 DepthTest,
+                DescriptorType,
                 InputAssemblerDesc,
+                PipelineStage,
                 Primitive,
+                Rect,
                 ShaderStageFlags,
                 VertexInputRate,
+                Viewport,
             },
         },
         mesh::AsVertex,
         resource::{
+            DescriptorSet,
+            DescriptorSetLayout,
+            Escape,
             Filter,
+            Handle as RendyHandle,
             Lod,
             PackedColor,
             SamplerDesc,
@@ -61,6 +100,7 @@ use amethyst_rendy::{
         texture::TextureBuilder,
     },
     submodules::{
+        DynamicIndexBuffer,
         DynamicUniform,
         DynamicVertexBuffer,
         TextureId,
@@ -68,6 +108,11 @@ use amethyst_rendy::{
     },
     system::GraphAuxData,
     types::TextureData,
+    util::{
+        desc_write,
+        set_layout_bindings,
+        texture_desc,
+    },
     Backend,
     ChangeDetection,
     Factory,
@@ -76,7 +121,7 @@ use amethyst_rendy::{
     RenderGroupDesc,
     Texture,
 };
-use amethyst_window::ScreenDimensions;
+use derivative::Derivative;
 use egui::{
     ClippedMesh,
     Color32,
@@ -89,9 +134,12 @@ use crate::{
         EguiViewArgs,
     },
     system::{
+        DefaultEgui,
         EguiContext,
+        EguiKey,
         EguiStage,
     },
+    world::EguiWorldPanel,
 };
 
 lazy_static::lazy_static! {
@@ -114,10 +162,30 @@ lazy_static::lazy_static! {
         .unwrap();
 }
 
-#[derive(Clone, Debug, Default, PartialEq)]
-pub struct DrawEguiDesc;
+#[derive(Derivative)]
+#[derivative(
+    Clone(bound = ""),
+    Debug(bound = ""),
+    Default(bound = ""),
+    PartialEq(bound = "")
+)]
+pub struct DrawEguiDesc<K = DefaultEgui> {
+    world_space: bool,
+    _key: PhantomData<K>,
+}
 
-impl<B: Backend> RenderGroupDesc<B, GraphAuxData> for DrawEguiDesc {
+impl<K> DrawEguiDesc<K> {
+    /// Draws the UI on screen, or if `world_space` is set, on every entity with an
+    /// `EguiWorldPanel<K>`.
+    pub fn new(world_space: bool) -> Self {
+        Self {
+            world_space,
+            _key: PhantomData,
+        }
+    }
+}
+
+impl<B: Backend, K: EguiKey> RenderGroupDesc<B, GraphAuxData> for DrawEguiDesc<K> {
     fn build<'a>(
         self,
         _ctx: &GraphContext<B>,
@@ -132,68 +200,319 @@ impl<B: Backend> RenderGroupDesc<B, GraphAuxData> for DrawEguiDesc {
     ) -> Result<Box<dyn RenderGroup<B, GraphAuxData>>, CreationError> {
         let env = DynamicUniform::new(factory, ShaderStageFlags::VERTEX)?;
         let textures = TextureSub::new(factory)?;
+        let font_atlas_layout = factory
+            .create_descriptor_set_layout(set_layout_bindings(vec![(
+                1,
+                DescriptorType::CombinedImageSampler,
+                ShaderStageFlags::FRAGMENT,
+            )]))?
+            .into();
         let vertex = DynamicVertexBuffer::new();
+        let index = DynamicIndexBuffer::new();
 
         let (pipeline, pipeline_layout) = build_egui_pipeline(
             factory,
             subpass,
             framebuffer_width,
             framebuffer_height,
+            self.world_space,
             vec![env.raw_layout(), textures.raw_layout()],
         )?;
 
-        Ok(Box::new(DrawEgui::<B> {
+        Ok(Box::new(DrawEgui::<B, K> {
             pipeline,
             pipeline_layout,
             env,
             textures,
             vertex,
+            index,
             change: Default::default(),
-            batch: Default::default(),
-            egui_texture: None,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            draws: Vec::new(),
+            uploaded_bytes: 0,
+            framebuffer_width,
+            framebuffer_height,
+            world_space: self.world_space,
+            models: Vec::new(),
+            font_atlas_layout,
+            font_atlas: None,
+            pending_font_atlas: None,
+            atlas_uploads: 0,
+            _key: PhantomData,
         }))
     }
 }
 
 #[derive(Debug)]
-pub struct DrawEgui<B: Backend> {
+pub struct DrawEgui<B: Backend, K: EguiKey = DefaultEgui> {
     pipeline: B::GraphicsPipeline,
     pipeline_layout: B::PipelineLayout,
     env: DynamicUniform<B, EguiViewArgs>,
     textures: TextureSub<B>,
     vertex: DynamicVertexBuffer<B, EguiArgs>,
-    batch: OrderedOneLevelBatch<TextureId, EguiArgs>,
+    index: DynamicIndexBuffer<B, u32>,
+    vertices: Vec<EguiArgs>,
+    indices: Vec<u32>,
+    draws: Vec<EguiDraw>,
+    uploaded_bytes: u64,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    world_space: bool,
+    /// Model matrix and clip rectangle of every panel the UI is drawn on.
+    models: Vec<(Matrix4<f32>, [f32; 4])>,
     change: ChangeDetection,
-    egui_texture: Option<(Handle<Texture>, u64)>,
+    /// Layout of the font atlas' descriptor set. It's the same as that of `textures`.
+    font_atlas_layout: RendyHandle<DescriptorSetLayout<B>>,
+    font_atlas: Option<FontAtlas<B>>,
+    /// A font atlas of a new size that is still loading.
+    pending_font_atlas: Option<PendingFontAtlas>,
+    atlas_uploads: u64,
+    _key: PhantomData<K>,
+}
+
+/// The font atlas that is drawn. It has its own descriptor set instead of being kept in the
+/// `TextureSub`, which can't remove textures, so that it is released when it's replaced.
+#[derive(Debug)]
+struct FontAtlas<B: Backend> {
+    handle: Handle<Texture>,
+    version: u64,
+    size: (usize, usize),
+    set: Escape<DescriptorSet<B>>,
 }
 
-impl<B: Backend> DrawEgui<B> {
-    fn upload_egui_texture(&mut self, texture: &egui::Texture, aux: &GraphAuxData) {
-        if self
-            .egui_texture
+#[derive(Debug)]
+struct PendingFontAtlas {
+    handle: Handle<Texture>,
+    version: u64,
+    size: (usize, usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DrawTexture {
+    FontAtlas,
+    User(TextureId),
+}
+
+/// A range of indices that is drawn with the same texture and clip rectangle.
+#[derive(Clone, Debug, PartialEq)]
+struct EguiDraw {
+    texture: DrawTexture,
+    scissor: Rect,
+    clip_rect: [f32; 4],
+    /// Bounding rectangle of the vertices, used to skip draws outside of world panels.
+    bounds: [f32; 4],
+    indices: Range<u32>,
+}
+
+/// Statistics about the last egui frame of context `K`, updated by `EguiSystem` and `DrawEgui`.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""), Default(bound = ""))]
+pub struct EguiRenderStats<K = DefaultEgui> {
+    /// Number of bytes uploaded to the vertex and index buffers.
+    pub uploaded_bytes: u64,
+    /// Total number of times the font atlas was uploaded.
+    pub atlas_uploads: u64,
+    /// Time spent in `CtxRef::begin_frame`.
+    pub begin_frame_time: Duration,
+    /// Time spent in `CtxRef::end_frame`.
+    pub end_frame_time: Duration,
+    /// Time spent in `CtxRef::tessellate`.
+    pub tessellate_time: Duration,
+    /// Time spent uploading the font atlas, vertices and indices.
+    pub upload_time: Duration,
+    /// Number of meshes egui tessellated.
+    pub meshes: usize,
+    /// Number of draw calls after merging meshes.
+    pub draws: usize,
+    pub vertices: usize,
+    pub indices: usize,
+    /// Number of distinct textures drawn.
+    pub textures: usize,
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<K>,
+}
+
+impl<B: Backend, K: EguiKey> DrawEgui<B, K> {
+    /// Returns the number of bytes uploaded to the vertex and index buffers in the last frame.
+    pub fn uploaded_bytes(&self) -> u64 {
+        self.uploaded_bytes
+    }
+
+    /// Returns the total number of times the font atlas was uploaded.
+    pub fn atlas_uploads(&self) -> u64 {
+        self.atlas_uploads
+    }
+
+    /// Updates the font atlas, if egui changed it. Glyphs added to an atlas of the same size are
+    /// written into the drawn atlas. An atlas of a new size is loaded as a new texture, and the old
+    /// one is drawn until it is loaded, see `swap_font_atlas`.
+    fn update_font_atlas(
+        &mut self,
+        factory: &Factory<B>,
+        queue: QueueId,
+        texture: &egui::Texture,
+        aux: &GraphAuxData,
+    ) {
+        let version = self
+            .pending_font_atlas
             .as_ref()
-            .map(|(_, version)| *version != texture.version)
-            .unwrap_or(true)
-        {
-            log::debug!("Egui texture changed: new_version={}", texture.version);
-
-            let loader = aux
-                .resources
-                .get::<DefaultLoader>()
-                .expect("default loader");
-            let texture_storage = aux
-                .resources
-                .get::<ProcessingQueue<TextureData>>()
-                .expect("texture storage");
-
-            let texture_data = convert_into_amethyst_texture(texture);
-            //let texture_data = load_from_linear_rgba(LinSrgba::new(1.0, 0.0, 0.0,
-            // 1.0)).into();
-
-            let handle = loader.load_from_data(texture_data, (), &texture_storage);
-            self.egui_texture = Some((handle, texture.version));
+            .map(|pending| pending.version)
+            .or_else(|| self.font_atlas.as_ref().map(|atlas| atlas.version));
+        if version == Some(texture.version) {
+            return;
         }
+
+        log::debug!("Egui texture changed: new_version={}", texture.version);
+        self.atlas_uploads += 1;
+
+        let size = (texture.width, texture.height);
+        if self.pending_font_atlas.is_none() {
+            if let Some(atlas) = self.font_atlas.as_mut().filter(|atlas| atlas.size == size) {
+                if write_font_atlas(factory, queue, &atlas.handle, texture, aux) {
+                    atlas.version = texture.version;
+                    return;
+                }
+            }
+        }
+
+        let loader = aux
+            .resources
+            .get::<DefaultLoader>()
+            .expect("default loader");
+        let texture_storage = aux
+            .resources
+            .get::<ProcessingQueue<TextureData>>()
+            .expect("texture storage");
+
+        let handle =
+            loader.load_from_data(convert_into_amethyst_texture(texture), (), &texture_storage);
+        self.pending_font_atlas = Some(PendingFontAtlas {
+            handle,
+            version: texture.version,
+            size,
+        });
+    }
+
+    /// Replaces the font atlas with the pending one once it is loaded. The old atlas and its
+    /// descriptor set are released. Returns whether the atlas was replaced.
+    fn swap_font_atlas(&mut self, factory: &Factory<B>, aux: &GraphAuxData) -> bool {
+        let set = self.pending_font_atlas.as_ref().and_then(|pending| {
+            create_texture_set(factory, aux, &self.font_atlas_layout, &pending.handle)
+        });
+
+        match (set, self.pending_font_atlas.take()) {
+            (Some(set), Some(pending)) => {
+                self.font_atlas = Some(FontAtlas {
+                    handle: pending.handle,
+                    version: pending.version,
+                    size: pending.size,
+                    set,
+                });
+                true
+            }
+            (_, pending) => {
+                self.pending_font_atlas = pending;
+                false
+            }
+        }
+    }
+}
+
+/// Creates a descriptor set for the texture `handle`, or returns `None` while it is loading.
+fn create_texture_set<B: Backend>(
+    factory: &Factory<B>,
+    aux: &GraphAuxData,
+    layout: &RendyHandle<DescriptorSetLayout<B>>,
+    handle: &Handle<Texture>,
+) -> Option<Escape<DescriptorSet<B>>> {
+    let texture_storage = aux.resources.get::<AssetStorage<Texture>>()?;
+    let descriptor = texture_desc(texture_storage.get(handle)?, Layout::ShaderReadOnlyOptimal)?;
+
+    let set = match factory.create_descriptor_set(layout.clone()) {
+        Ok(set) => set,
+        Err(e) => {
+            log::error!("Failed to create descriptor set: {:?}", e);
+            return None;
+        }
+    };
+    unsafe {
+        factory.write_descriptor_sets(vec![desc_write(set.raw(), 0, descriptor)]);
+    }
+    Some(set)
+}
+
+/// Writes egui's font atlas into the loaded atlas texture `handle` of the same size. Returns
+/// whether the upload was queued.
+///
+/// The upload is submitted before the next frame, so glyphs added in this frame are blank for one
+/// frame. The other glyphs don't move.
+fn write_font_atlas<B: Backend>(
+    factory: &Factory<B>,
+    queue: QueueId,
+    handle: &Handle<Texture>,
+    texture: &egui::Texture,
+    aux: &GraphAuxData,
+) -> bool {
+    let texture_storage = match aux.resources.get::<AssetStorage<Texture>>() {
+        Some(texture_storage) => texture_storage,
+        None => return false,
+    };
+    let image = match texture_storage.get(handle).and_then(B::unwrap_texture) {
+        Some(atlas) => atlas.image().clone(),
+        None => return false,
+    };
+
+    // The state the texture processor leaves textures in.
+    let state = ImageState {
+        queue,
+        stage: PipelineStage::VERTEX_SHADER | PipelineStage::FRAGMENT_SHADER,
+        access: Access::SHADER_READ,
+        layout: Layout::ShaderReadOnlyOptimal,
+    };
+    let (width, height) = (texture.width as u32, texture.height as u32);
+
+    let result = unsafe {
+        factory.upload_image(
+            image,
+            width,
+            height,
+            SubresourceLayers {
+                aspects: Aspects::COLOR,
+                level: 0,
+                layers: 0..1,
+            },
+            Offset::ZERO,
+            Extent {
+                width,
+                height,
+                depth: 1,
+            },
+            &font_atlas_pixels(texture),
+            state,
+            state,
+        )
+    };
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            log::error!("Failed to upload font atlas: {:?}", e);
+            false
+        }
+    }
+}
+
+/// Converts egui's font atlas, which only has coverage, into white sRGBA pixels.
+fn font_atlas_pixels(texture: &egui::Texture) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(texture.pixels.len() * 4);
+    for a in &texture.pixels {
+        let t = Color32::from_white_alpha(*a).to_tuple();
+        pixels.push(t.0);
+        pixels.push(t.1);
+        pixels.push(t.2);
+        pixels.push(t.3);
     }
+    pixels
 }
 
 fn convert_into_amethyst_texture(texture: &egui::Texture) -> TextureData {
@@ -218,31 +537,53 @@ fn convert_into_amethyst_texture(texture: &egui::Texture) -> TextureData {
         anisotropy_clamp: None,
     });
 
-    let mut pixels = vec![];
-    for a in &texture.pixels {
-        let t = Color32::from_white_alpha(*a).to_tuple();
-        pixels.push(t.0);
-        pixels.push(t.1);
-        pixels.push(t.2);
-        pixels.push(t.3);
-    }
-    b.set_raw_data(Cow::Owned(pixels), Format::Rgba8Srgb);
+    b.set_raw_data(Cow::Owned(font_atlas_pixels(texture)), Format::Rgba8Srgb);
 
     TextureData(b)
 }
 
-impl<B: Backend> RenderGroup<B, GraphAuxData> for DrawEgui<B> {
+/// Converts an egui clip rectangle (in points) into a scissor rectangle (in framebuffer pixels),
+/// clamped to the framebuffer.
+fn clip_rect_to_scissor(
+    clip_rect: egui::Rect,
+    pixels_per_point: f32,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+) -> Rect {
+    let to_pixels = |points: f32, max: u32| {
+        (points * pixels_per_point)
+            .round()
+            .max(0.0)
+            .min(max as f32)
+    };
+    let clamp_x = |x: f32| to_pixels(x, framebuffer_width);
+    let clamp_y = |y: f32| to_pixels(y, framebuffer_height);
+
+    let min_x = clamp_x(clip_rect.min.x);
+    let min_y = clamp_y(clip_rect.min.y);
+    let max_x = clamp_x(clip_rect.max.x).max(min_x);
+    let max_y = clamp_y(clip_rect.max.y).max(min_y);
+
+    Rect {
+        x: min_x as i16,
+        y: min_y as i16,
+        w: (max_x - min_x) as i16,
+        h: (max_y - min_y) as i16,
+    }
+}
+
+impl<B: Backend, K: EguiKey> RenderGroup<B, GraphAuxData> for DrawEgui<B, K> {
     fn prepare(
         &mut self,
         factory: &Factory<B>,
-        _queue: QueueId,
+        queue: QueueId,
         index: usize,
         _subpass: Subpass<'_, B>,
         aux: &GraphAuxData,
     ) -> PrepareResult {
         let mut egui_ctx = aux
             .resources
-            .get_mut::<EguiContext>()
+            .get_mut::<EguiContext<K>>()
             .expect("EguiState resource missing");
 
         if !matches!(&egui_ctx.stage, EguiStage::Render) {
@@ -252,64 +593,177 @@ impl<B: Backend> RenderGroup<B, GraphAuxData> for DrawEgui<B> {
 
         let mut changed = false;
 
-        self.upload_egui_texture(&egui_ctx.ctx.texture(), aux);
-
-        let screen_dimensions = aux.resources.get::<ScreenDimensions>().unwrap();
-
+        let start = Instant::now();
         let (egui_output, shapes) = egui_ctx.ctx.end_frame();
         egui_ctx.stage = EguiStage::End(egui_output);
-        let clipped_meshes = egui_ctx.ctx.tessellate(shapes);
+        let end_frame_time = start.elapsed();
 
+        let start = Instant::now();
+        let clipped_meshes = egui_ctx.ctx.tessellate(shapes);
+        let tessellate_time = start.elapsed();
+        let mesh_count = clipped_meshes.len();
+
+        // Upload the font atlas after tessellation, so that it contains all glyphs of this frame.
+        let start = Instant::now();
+        self.update_font_atlas(factory, queue, &egui_ctx.ctx.texture(), aux);
+        changed = self.swap_font_atlas(factory, aux) || changed;
+        let mut upload_time = start.elapsed();
+
+        let pixels_per_point = egui_ctx.ctx.pixels_per_point();
+        let screen_size = egui_ctx.ctx.input().screen_rect().size();
+        let old_draws = std::mem::take(&mut self.draws);
+        self.vertices.clear();
+        self.indices.clear();
         let textures_ref = &mut self.textures;
-        let batch_ref = &mut self.batch;
-        batch_ref.swap_clear();
 
-        for ClippedMesh(_clip_rect, mesh) in clipped_meshes {
+        for ClippedMesh(clip_rect, mesh) in clipped_meshes {
+            let scissor = if self.world_space {
+                // In world space the clip rectangle isn't a screen rectangle, so it's only applied
+                // in the fragment shader.
+                Rect {
+                    x: 0,
+                    y: 0,
+                    w: self.framebuffer_width as i16,
+                    h: self.framebuffer_height as i16,
+                }
+            }
+            else {
+                clip_rect_to_scissor(
+                    clip_rect,
+                    pixels_per_point,
+                    self.framebuffer_width,
+                    self.framebuffer_height,
+                )
+            };
+            if scissor.w == 0 || scissor.h == 0 {
+                continue;
+            }
+            let clip_rect = rect_to_array(clip_rect);
+
             let texture = match &mesh.texture_id {
                 egui::epaint::TextureId::Egui => {
-                    self.egui_texture.as_ref().map(|(handle, _)| handle)
+                    if self.font_atlas.is_none() {
+                        log::debug!("Font atlas not loaded yet");
+                        continue;
+                    }
+                    DrawTexture::FontAtlas
+                }
+                egui::epaint::TextureId::User(id) => {
+                    let handle = if let Some(handle) = egui_ctx.user_textures.get(id) {
+                        handle
+                    }
+                    else {
+                        log::error!("Texture not registered: {:?}", mesh.texture_id);
+                        continue;
+                    };
+
+                    // `TextureSub::insert` returns `None` while the texture asset is still loading.
+                    if let Some((tex_id, this_changed)) = textures_ref.insert(
+                        factory,
+                        aux.resources,
+                        handle,
+                        Layout::ShaderReadOnlyOptimal,
+                    ) {
+                        changed = changed || this_changed;
+                        DrawTexture::User(tex_id)
+                    }
+                    else {
+                        log::debug!("Texture not loaded yet: {:?}", mesh.texture_id);
+                        continue;
+                    }
                 }
-                egui::epaint::TextureId::User(id) => egui_ctx.user_textures.get(id),
             };
 
-            if let Some((tex_id, this_changed)) = texture.and_then(|texture| {
-                textures_ref.insert(
-                    factory,
-                    aux.resources,
-                    texture,
-                    Layout::ShaderReadOnlyOptimal,
-                )
-            }) {
-                changed = changed || this_changed;
-
-                let vertices = mesh.vertices;
-                //log::debug!("num vertices: {}", vertices.len());
-                batch_ref.insert(
-                    tex_id,
-                    mesh.indices.into_iter().map(|index| {
-                        let vertex = vertices[index as usize];
-                        //log::debug!("{:?}", vertex);
-                        EguiArgs::new(vertex)
-                    }),
-                );
-            }
-            else {
-                log::error!("Texture missing: {:?}", texture);
+            let bounds = mesh_bounds(&mesh);
+
+            // Indices are offset, so that all meshes can share one vertex buffer.
+            let base_vertex = self.vertices.len() as u32;
+            let start = self.indices.len() as u32;
+            self.vertices.extend(mesh.vertices.into_iter().map(EguiArgs::new));
+            self.indices.extend(mesh.indices.into_iter().map(|index| base_vertex + index));
+            let end = self.indices.len() as u32;
+
+            // Meshes are drawn in order, so we can only merge with the previous draw. In world
+            // space, egui's meshes are kept apart, so that those of other panels can be skipped.
+            match self.draws.last_mut() {
+                Some(draw)
+                    if !self.world_space
+                        && draw.texture == texture
+                        && draw.clip_rect == clip_rect
+                        && draw.indices.end == start =>
+                {
+                    draw.bounds = union_rects(&draw.bounds, &bounds);
+                    draw.indices.end = end;
+                }
+                _ => {
+                    self.draws.push(EguiDraw {
+                        texture,
+                        scissor,
+                        clip_rect,
+                        bounds,
+                        indices: start..end,
+                    });
+                }
             }
         }
 
         self.textures.maintain(factory, aux.resources);
-        changed = changed || self.batch.changed();
+        changed = changed || self.draws != old_draws;
 
         {
+            let start = Instant::now();
             self.vertex.write(
                 factory,
                 index,
-                self.batch.count() as u64,
-                Some(self.batch.data()),
+                self.vertices.len() as u64,
+                Some(&self.vertices),
             );
+            self.index.write(
+                factory,
+                index,
+                self.indices.len() as u64,
+                Some(&self.indices),
+            );
+            upload_time += start.elapsed();
+
+            self.uploaded_bytes = (self.vertices.len() * std::mem::size_of::<EguiArgs>()
+                + self.indices.len() * std::mem::size_of::<u32>())
+                as u64;
+            if let Some(mut stats) = aux.resources.get_mut::<EguiRenderStats<K>>() {
+                stats.uploaded_bytes = self.uploaded_bytes;
+                stats.atlas_uploads = self.atlas_uploads;
+                stats.end_frame_time = end_frame_time;
+                stats.tessellate_time = tessellate_time;
+                stats.upload_time = upload_time;
+                stats.meshes = mesh_count;
+                stats.draws = self.draws.len();
+                stats.vertices = self.vertices.len();
+                stats.indices = self.indices.len();
+                let mut textures = Vec::new();
+                for draw in &self.draws {
+                    if !textures.contains(&draw.texture) {
+                        textures.push(draw.texture);
+                    }
+                }
+                stats.textures = textures.len();
+            }
 
-            let view_args = EguiViewArgs::new(egui::Rect::EVERYTHING, &screen_dimensions);
+            let old_models = std::mem::take(&mut self.models);
+            let view_args = if self.world_space {
+                // Without a camera the panels have nowhere to be drawn.
+                match camera_matrix(aux) {
+                    Some(camera) => {
+                        self.models = gather_world_panels::<K>(aux);
+                        EguiViewArgs::from_matrix(&camera)
+                    }
+                    None => EguiViewArgs::from_matrix(&Matrix4::identity()),
+                }
+            }
+            else {
+                self.models.push((Matrix4::identity(), rect_to_array(egui::Rect::EVERYTHING)));
+                EguiViewArgs::new(screen_size)
+            };
+            changed = changed || self.models != old_models;
             changed = self.env.write(factory, index, view_args.std140()) || changed;
         }
 
@@ -327,13 +781,51 @@ impl<B: Backend> RenderGroup<B, GraphAuxData> for DrawEgui<B> {
 
         self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
         self.vertex.bind(index, 0, 0, &mut encoder);
-        //self.index.bind(index, 0, &mut encoder);
+        self.index.bind(index, 0, &mut encoder);
 
-        for (&tex, range) in self.batch.iter() {
-            self.textures
-                .bind(&self.pipeline_layout, 1, tex, &mut encoder);
+        for (model, panel_rect) in &self.models {
             unsafe {
-                encoder.draw(range, 0..1);
+                encoder.push_constants(
+                    &self.pipeline_layout,
+                    ShaderStageFlags::VERTEX,
+                    0,
+                    &matrix_to_push_constants(model),
+                );
+            }
+
+            for draw in &self.draws {
+                // Draws outside of the panel aren't issued. The fragment shader clips those
+                // overlapping its edges.
+                let clip_rect = intersect_clip_rects(&draw.clip_rect, panel_rect);
+                if is_empty_rect(&intersect_clip_rects(&clip_rect, &draw.bounds)) {
+                    continue;
+                }
+
+                match (draw.texture, &self.font_atlas) {
+                    (DrawTexture::FontAtlas, Some(font_atlas)) => unsafe {
+                        encoder.bind_graphics_descriptor_sets(
+                            &self.pipeline_layout,
+                            1,
+                            Some(font_atlas.set.raw()),
+                            std::iter::empty(),
+                        );
+                    },
+                    (DrawTexture::FontAtlas, None) => continue,
+                    (DrawTexture::User(texture), _) => {
+                        self.textures
+                            .bind(&self.pipeline_layout, 1, texture, &mut encoder);
+                    }
+                }
+                unsafe {
+                    encoder.push_constants(
+                        &self.pipeline_layout,
+                        ShaderStageFlags::FRAGMENT,
+                        MODEL_PUSH_CONSTANTS_SIZE,
+                        &clip_rect_to_push_constants(&clip_rect),
+                    );
+                    encoder.set_scissors(0, &[draw.scissor]);
+                    encoder.draw_indexed(draw.indices.clone(), 0, 0..1);
+                }
             }
         }
     }
@@ -348,19 +840,121 @@ impl<B: Backend> RenderGroup<B, GraphAuxData> for DrawEgui<B> {
     }
 }
 
+/// Size of the model matrix at the start of the push constants. It's followed by the clip rectangle
+/// for the fragment shader.
+const MODEL_PUSH_CONSTANTS_SIZE: u32 = 64;
+const CLIP_RECT_PUSH_CONSTANTS_SIZE: u32 = 16;
+
+fn matrix_to_push_constants(matrix: &Matrix4<f32>) -> [u32; 16] {
+    let mut constants = [0; 16];
+    for (constant, value) in constants.iter_mut().zip(matrix.iter()) {
+        *constant = value.to_bits();
+    }
+    constants
+}
+
+fn clip_rect_to_push_constants(clip_rect: &[f32; 4]) -> [u32; 4] {
+    [
+        clip_rect[0].to_bits(),
+        clip_rect[1].to_bits(),
+        clip_rect[2].to_bits(),
+        clip_rect[3].to_bits(),
+    ]
+}
+
+fn rect_to_array(rect: egui::Rect) -> [f32; 4] {
+    [rect.min.x, rect.min.y, rect.max.x, rect.max.y]
+}
+
+fn intersect_clip_rects(a: &[f32; 4], b: &[f32; 4]) -> [f32; 4] {
+    [a[0].max(b[0]), a[1].max(b[1]), a[2].min(b[2]), a[3].min(b[3])]
+}
+
+fn union_rects(a: &[f32; 4], b: &[f32; 4]) -> [f32; 4] {
+    [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])]
+}
+
+fn is_empty_rect(rect: &[f32; 4]) -> bool {
+    rect[0] >= rect[2] || rect[1] >= rect[3]
+}
+
+/// Returns the bounding rectangle of the vertices of `mesh`, which is empty if it has none.
+fn mesh_bounds(mesh: &egui::epaint::Mesh) -> [f32; 4] {
+    mesh.vertices.iter().fold(
+        [
+            std::f32::INFINITY,
+            std::f32::INFINITY,
+            std::f32::NEG_INFINITY,
+            std::f32::NEG_INFINITY,
+        ],
+        |bounds, vertex| {
+            union_rects(
+                &bounds,
+                &[vertex.pos.x, vertex.pos.y, vertex.pos.x, vertex.pos.y],
+            )
+        },
+    )
+}
+
+/// Returns the model matrices and shown rectangles of all world panels of the context `K`.
+fn gather_world_panels<K: EguiKey>(aux: &GraphAuxData) -> Vec<(Matrix4<f32>, [f32; 4])> {
+    <(Read<EguiWorldPanel<K>>, Read<Transform>)>::query()
+        .iter(aux.world)
+        .map(|(panel, transform)| {
+            (
+                transform.global_matrix() * panel.local_matrix(),
+                rect_to_array(panel.rect()),
+            )
+        })
+        .collect()
+}
+
+/// Returns the projection-view matrix of the active camera, or of the first camera if none is
+/// active.
+fn camera_matrix(aux: &GraphAuxData) -> Option<Matrix4<f32>> {
+    let active_camera = aux
+        .resources
+        .get::<ActiveCamera>()
+        .and_then(|active_camera| active_camera.entity);
+
+    <(Entity, Read<Camera>, Read<Transform>)>::query()
+        .iter(aux.world)
+        .find(|(entity, _, _)| active_camera.map_or(true, |active| **entity == active))
+        .map(|(_, camera, transform)| camera.as_matrix() * transform.global_view_matrix())
+}
+
 fn build_egui_pipeline<B: Backend>(
     factory: &Factory<B>,
     subpass: Subpass<'_, B>,
     framebuffer_width: u32,
     framebuffer_height: u32,
+    world_space: bool,
     layouts: Vec<&B::DescriptorSetLayout>,
 ) -> Result<(B::GraphicsPipeline, B::PipelineLayout), CreationError> {
+    let push_constants = vec![
+        (ShaderStageFlags::VERTEX, 0..MODEL_PUSH_CONSTANTS_SIZE),
+        (
+            ShaderStageFlags::FRAGMENT,
+            MODEL_PUSH_CONSTANTS_SIZE..MODEL_PUSH_CONSTANTS_SIZE + CLIP_RECT_PUSH_CONSTANTS_SIZE,
+        ),
+    ];
     let pipeline_layout = unsafe {
         factory
             .device()
-            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+            .create_pipeline_layout(layouts, push_constants)
     }?;
 
+    // World panels are hidden behind the scene, but don't occlude it, since they're transparent.
+    let depth_test = if world_space {
+        DepthTest {
+            fun: Comparison::LessEqual,
+            write: false,
+        }
+    }
+    else {
+        DepthTest::PASS_TEST
+    };
+
     let mut shaders = EGUI_SHADERS
         .build(factory, Default::default())
         .map_err(|e| {
@@ -379,12 +973,12 @@ fn build_egui_pipeline<B: Backend>(
                 .with_shaders(shaders.raw().map_err(|_| CreationError::Other)?)
                 .with_layout(&pipeline_layout)
                 .with_subpass(subpass)
-                .with_framebuffer_size(framebuffer_width, framebuffer_height)
                 .with_blend_targets(vec![ColorBlendDesc {
                     mask: ColorMask::ALL,
                     blend: Some(BlendState::PREMULTIPLIED_ALPHA),
                 }])
-                /*.with_baked_states(BakedStates {
+                // The scissor rectangle is set per draw call from egui's clip rectangles.
+                .with_baked_states(BakedStates {
                     viewport: Some(Viewport {
                         rect: Rect {
                             x: 0,
@@ -392,12 +986,12 @@ fn build_egui_pipeline<B: Backend>(
                             w: framebuffer_width as i16,
                             h: framebuffer_height as i16,
                         },
-                        depth: 0.0 .. 1.0,
+                        depth: 0.0..1.0,
                     }),
                     scissor: None,
                     ..Default::default()
-                })*/
-                .with_depth_test(DepthTest::PASS_TEST),
+                })
+                .with_depth_test(depth_test),
         )
         .build(factory, None);
 
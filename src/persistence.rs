@@ -0,0 +1,233 @@
+use std::{
+    error::Error,
+    fs,
+    io,
+    marker::PhantomData,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::Duration,
+};
+
+use amethyst_core::{
+    ecs::{
+        ParallelRunnable,
+        System,
+        SystemBuilder,
+    },
+    Time,
+};
+use derivative::Derivative;
+use egui::{
+    CtxRef,
+    Memory,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::system::{
+    EguiContext,
+    EguiKey,
+};
+
+/// Version of the file format. Files with another version are ignored.
+const MEMORY_FORMAT_VERSION: u32 = 1;
+
+#[derive(Deserialize)]
+struct SavedVersion {
+    version: u32,
+}
+
+#[derive(Serialize)]
+struct SavedMemoryRef<'a> {
+    version: u32,
+    memory: &'a Memory,
+}
+
+#[derive(Deserialize)]
+struct SavedMemory {
+    memory: Memory,
+}
+
+/// Saves egui's `Memory`, i.e. window positions, panel sizes, collapsed headers etc., to a RON
+/// file and restores it on the next start. Add it with `EguiBundle::with_persistence`.
+///
+/// The memory is saved when the application shuts down and every `save_interval`. Each context
+/// needs its own file.
+#[derive(Clone, Debug)]
+pub struct EguiPersistence {
+    path: PathBuf,
+    save_interval: Option<Duration>,
+}
+
+impl Default for EguiPersistence {
+    /// Saves to `egui_memory.ron` in the application directory every 30 seconds.
+    fn default() -> Self {
+        Self::new(application_dir().join("egui_memory.ron"))
+    }
+}
+
+impl EguiPersistence {
+    /// Saves to `path` every 30 seconds.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            save_interval: Some(Duration::from_secs(30)),
+        }
+    }
+
+    /// Sets how often the memory is saved while the application is running. With `None` it is
+    /// only saved on shutdown.
+    pub fn with_save_interval(mut self, save_interval: Option<Duration>) -> Self {
+        self.save_interval = save_interval;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Loads the saved memory. Returns `None` if there is no file, or it is from another version
+    /// or corrupt, in which case a warning is logged.
+    pub fn load(&self) -> Option<Memory> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                log::warn!("Failed to read egui memory from {}: {}", self.path.display(), e);
+                return None;
+            }
+        };
+
+        let result = ron::de::from_str::<SavedVersion>(&text).and_then(|saved| {
+            if saved.version == MEMORY_FORMAT_VERSION {
+                ron::de::from_str::<SavedMemory>(&text).map(|saved| Some(saved.memory))
+            }
+            else {
+                log::warn!(
+                    "Ignoring egui memory in {} with version {}, expected {}",
+                    self.path.display(),
+                    saved.version,
+                    MEMORY_FORMAT_VERSION
+                );
+                Ok(None)
+            }
+        });
+
+        match result {
+            Ok(memory) => memory,
+            Err(e) => {
+                log::warn!("Ignoring corrupt egui memory in {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Saves `memory`. Errors are logged.
+    pub fn save(&self, memory: &Memory) {
+        if let Err(e) = self.try_save(memory) {
+            log::error!("Failed to save egui memory to {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn try_save(&self, memory: &Memory) -> Result<(), Box<dyn Error>> {
+        let text = ron::ser::to_string_pretty(
+            &SavedMemoryRef {
+                version: MEMORY_FORMAT_VERSION,
+                memory,
+            },
+            Default::default(),
+        )?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Write to a temporary file first, so that a crash doesn't leave a truncated file.
+        let temp_path = self.path.with_extension("ron.tmp");
+        fs::write(&temp_path, text)?;
+        fs::rename(&temp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+/// Returns the directory of the application, like `amethyst::utils::application_root_dir`.
+fn application_dir() -> PathBuf {
+    if let Some(manifest_dir) = std::env::var_os("CARGO_MANIFEST_DIR") {
+        return PathBuf::from(manifest_dir);
+    }
+
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .unwrap_or_default()
+}
+
+/// Saves the memory when the system is dropped, i.e. when the application shuts down.
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct MemorySaver {
+    persistence: EguiPersistence,
+    #[derivative(Debug = "ignore")]
+    ctx: CtxRef,
+}
+
+impl MemorySaver {
+    fn save(&self) {
+        let memory = self.ctx.memory().clone();
+        self.persistence.save(&memory);
+    }
+}
+
+impl Drop for MemorySaver {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
+
+/// Saves the memory of the egui context `K` periodically and on shutdown.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub(crate) struct PersistenceSystem<K> {
+    saver: MemorySaver,
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<K>,
+}
+
+impl<K> PersistenceSystem<K> {
+    pub fn new(persistence: EguiPersistence, ctx: CtxRef) -> Self {
+        Self {
+            saver: MemorySaver { persistence, ctx },
+            _key: PhantomData,
+        }
+    }
+}
+
+impl<K: EguiKey> System for PersistenceSystem<K> {
+    fn build(self) -> Box<dyn ParallelRunnable + 'static> {
+        let saver = self.saver;
+        let mut since_save = Duration::default();
+
+        Box::new(
+            SystemBuilder::new(format!("PersistenceSystem<{}>", std::any::type_name::<K>()))
+                .read_resource::<Time>()
+                // Not used, but orders the system with the other systems using the context.
+                .read_resource::<EguiContext<K>>()
+                .build(move |_commands, _world, resources, _queries| {
+                    let (time, _egui_ctx) = resources;
+
+                    if let Some(save_interval) = saver.persistence.save_interval {
+                        since_save += time.delta_real_time();
+                        if since_save >= save_interval {
+                            since_save = Duration::default();
+                            saver.save();
+                        }
+                    }
+                }),
+        )
+    }
+}
@@ -1,45 +1,159 @@
-use amethyst_core::ecs::{
-    Resources,
-    World,
+use std::marker::PhantomData;
+
+use amethyst_core::{
+    dispatcher::DispatcherBuilder,
+    ecs::{
+        Resources,
+        World,
+    },
 };
 use amethyst_error::Error;
 use amethyst_rendy::{
     bundle::{
+        ImageOptions,
+        OutputColor,
         RenderOrder,
         RenderPlan,
         Target,
+        TargetPlanOutputs,
+    },
+    rendy::hal::command::{
+        ClearColor,
+        ClearValue,
     },
     Backend,
     Factory,
+    Format,
+    Kind,
     RenderGroupDesc,
     RenderPlugin,
 };
+use derivative::Derivative;
 
-use crate::pass::DrawEguiDesc;
+use crate::{
+    inspector::{
+        ResourceInspectorScheduled,
+        ResourceInspectorWindow,
+    },
+    pass::DrawEguiDesc,
+    system::{
+        DefaultEgui,
+        EguiContext,
+        EguiKey,
+    },
+};
 
-#[derive(Default, Debug)]
-pub struct RenderEgui {
+/// Renders the egui context with key `K`.
+#[derive(Derivative)]
+#[derivative(Default(bound = ""), Debug(bound = ""))]
+pub struct RenderEgui<K = DefaultEgui> {
     target: Target,
+    offscreen: bool,
+    world_space: bool,
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<K>,
 }
 
-impl RenderEgui {
+impl RenderEgui<DefaultEgui> {
+    /// Renders the default context. Use `RenderEgui::<K>::default()` for other contexts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K: EguiKey> RenderEgui<K> {
     /// Select render target on which UI should be rendered.
     pub fn with_target(mut self, target: Target) -> Self {
         self.target = target;
         self
     }
+
+    /// Renders the UI into a new render target `target`, that is only used by the UI. The size of
+    /// the image is set with `EguiBundle::with_offscreen_size`.
+    ///
+    /// The image only exists in the render graph: custom render groups can sample it with
+    /// `TargetPlanContext::target_image(target, TargetImage::Color(0))`.
+    ///
+    /// This is not a `Handle<Texture>`, so materials can't use it. Texture assets can't be render
+    /// targets, and the render plan has no way to copy the image into one. To show UI on objects
+    /// in the world, e.g. terminals or signs, use `in_world` with `EguiWorldPanel` instead.
+    pub fn with_offscreen_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self.offscreen = true;
+        self
+    }
+
+    /// Renders the UI on all entities with an `EguiWorldPanel<K>`, instead of on screen. The UI is
+    /// depth tested against the scene of the render target.
+    pub fn in_world(mut self) -> Self {
+        self.world_space = true;
+        self
+    }
 }
 
-impl<B: Backend> RenderPlugin<B> for RenderEgui {
+impl<B: Backend, K: EguiKey> RenderPlugin<B> for RenderEgui<K> {
+    fn on_build(
+        &mut self,
+        _world: &mut World,
+        resources: &mut Resources,
+        builder: &mut DispatcherBuilder,
+    ) -> Result<(), Error> {
+        // Plugins are built before `RenderingBundle` adds the render system, so the inspector is
+        // always shown before the frame ends, no matter when `EguiBundle` is added.
+        if !resources.contains::<ResourceInspectorScheduled<K>>() {
+            resources.insert(ResourceInspectorScheduled::<K>::default());
+            builder.add_thread_local_fn(|_world, resources| {
+                ResourceInspectorWindow::<K>::show(resources)
+            });
+        }
+        Ok(())
+    }
+
     fn on_plan(
         &mut self,
         plan: &mut RenderPlan<B>,
         _factory: &mut Factory<B>,
         _world: &World,
-        _resources: &Resources,
+        resources: &Resources,
     ) -> Result<(), Error> {
-        plan.extend_target(self.target, |ctx| {
-            ctx.add(RenderOrder::Overlay, DrawEguiDesc::default().builder())?;
+        if self.offscreen {
+            let (width, height) = resources
+                .get::<EguiContext<K>>()
+                .and_then(|egui_ctx| egui_ctx.offscreen_size())
+                .ok_or_else(|| {
+                    Error::from_string(
+                        "Offscreen egui target requires `EguiBundle::with_offscreen_size`",
+                    )
+                })?;
+
+            plan.add_root(self.target);
+            plan.define_pass(
+                self.target,
+                TargetPlanOutputs {
+                    colors: vec![OutputColor::Image(ImageOptions {
+                        kind: Kind::D2(width, height, 1, 1),
+                        levels: 1,
+                        format: Format::Rgba8Srgb,
+                        clear: Some(ClearValue {
+                            color: ClearColor {
+                                float32: [0.0, 0.0, 0.0, 0.0],
+                            },
+                        }),
+                    })],
+                    depth: None,
+                },
+            )?;
+        }
+
+        let world_space = self.world_space;
+        plan.extend_target(self.target, move |ctx| {
+            let order = if world_space {
+                RenderOrder::Transparent
+            }
+            else {
+                RenderOrder::Overlay
+            };
+            ctx.add(order, DrawEguiDesc::<K>::new(world_space).builder())?;
             Ok(())
         });
         Ok(())
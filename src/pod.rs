@@ -9,38 +9,28 @@ use amethyst_rendy::{
     },
     Format,
 };
-use amethyst_window::ScreenDimensions;
 use egui::epaint;
 use glsl_layout::{
     mat4,
-    vec2,
-    vec4,
     Uniform,
 };
 
-#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Uniform)]
-#[repr(C, align(16))]
+/// Vertex as uploaded to the GPU. The color is egui's premultiplied sRGBA, which is converted to
+/// linear space in the vertex shader.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C)]
 pub(crate) struct EguiArgs {
-    pub pos: vec2,
-    pub uv: vec2,
-    pub color: vec4,
+    pub pos: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [u8; 4],
 }
 
 impl EguiArgs {
     pub fn new(vertex: epaint::Vertex) -> Self {
-        //let color = epaint::Rgba::from(vertex.color);
-        //log::debug!("{:?}", vertex.uv)
         Self {
-            pos: [vertex.pos.x, vertex.pos.y].into(),
-            uv: [vertex.uv.x, vertex.uv.y].into(),
-            //color: [color.r(), color.g(), color.b(), color.a()].into(),
-            color: [
-                vertex.color.r() as f32,
-                vertex.color.g() as f32,
-                vertex.color.b() as f32,
-                vertex.color.a() as f32,
-            ]
-            .into(),
+            pos: [vertex.pos.x, vertex.pos.y],
+            uv: [vertex.uv.x, vertex.uv.y],
+            color: vertex.color.to_array(),
         }
     }
 }
@@ -50,7 +40,7 @@ impl AsVertex for EguiArgs {
         VertexFormat::new((
             (Format::Rg32Sfloat, "pos"),
             (Format::Rg32Sfloat, "uv"),
-            (Format::Rgba32Sfloat, "color"),
+            (Format::Rgba8Unorm, "color"),
         ))
     }
 }
@@ -58,30 +48,62 @@ impl AsVertex for EguiArgs {
 #[derive(Clone, Copy, Debug, Uniform)]
 #[repr(C, align(16))]
 pub(crate) struct EguiViewArgs {
-    pub clip_rect: vec4,
     pub view: mat4,
 }
 
 impl EguiViewArgs {
-    pub fn new(clip_rect: egui::Rect, screen_dimensions: &ScreenDimensions) -> Self {
+    /// Creates the view arguments for a screen of `screen_size` logical points (i.e. the size of
+    /// egui's `screen_rect`).
+    pub fn new(screen_size: egui::Vec2) -> Self {
         let mut view = Matrix4::identity();
         view.append_nonuniform_scaling_mut(&Vector3::new(
-            2.0 / screen_dimensions.width() as f32,
-            2.0 / screen_dimensions.height() as f32,
+            2.0 / screen_size.x,
+            2.0 / screen_size.y,
             1.0,
         ));
         view.append_translation_mut(&Vector3::new(-1.0, -1.0, 0.0));
         let view: [[f32; 4]; 4] = view.into();
 
-        Self {
-            clip_rect: [
-                clip_rect.min.x,
-                clip_rect.min.y,
-                clip_rect.max.x,
-                clip_rect.max.y,
-            ]
-            .into(),
-            view: view.into(),
-        }
+        Self { view: view.into() }
+    }
+
+    /// Creates the view arguments from a projection-view matrix, e.g. for world space UI.
+    pub fn from_matrix(view: &Matrix4<f32>) -> Self {
+        let view: [[f32; 4]; 4] = (*view).into();
+        Self { view: view.into() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vertex_format_is_packed() {
+        let format = EguiArgs::vertex();
+        assert_eq!(format.stride, 20);
+        assert_eq!(std::mem::size_of::<EguiArgs>(), 20);
+
+        let color = format
+            .attributes
+            .iter()
+            .find(|attribute| attribute.name() == "color")
+            .expect("color attribute");
+        assert_eq!(color.element().format, Format::Rgba8Unorm);
+        assert_eq!(color.element().offset, 16);
+    }
+
+    #[test]
+    fn color_round_trip() {
+        let color = egui::Color32::from_rgba_premultiplied(12, 34, 56, 78);
+        let args = EguiArgs::new(epaint::Vertex {
+            pos: egui::pos2(1.0, 2.0),
+            uv: egui::pos2(0.25, 0.75),
+            color,
+        });
+
+        assert_eq!(args.color, [12, 34, 56, 78]);
+        let [r, g, b, a] = args.color;
+        assert_eq!(egui::Color32::from_rgba_premultiplied(r, g, b, a), color);
     }
 }
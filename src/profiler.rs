@@ -0,0 +1,267 @@
+use std::{
+    collections::VecDeque,
+    marker::PhantomData,
+    time::Duration,
+};
+
+use amethyst_core::{
+    ecs::{
+        ParallelRunnable,
+        System,
+        SystemBuilder,
+    },
+    Time,
+};
+use derivative::Derivative;
+use egui::{
+    Color32,
+    Pos2,
+    Rect,
+    Sense,
+    Shape,
+    Stroke,
+    Ui,
+    Vec2,
+};
+
+use crate::{
+    pass::EguiRenderStats,
+    system::{
+        EguiContext,
+        EguiKey,
+    },
+};
+
+const FRAME_TIME_COLOR: Color32 = Color32::from_rgb(100, 200, 100);
+const EGUI_TIME_COLOR: Color32 = Color32::from_rgb(200, 150, 50);
+
+/// An overlay showing frame times and how much of them is spent on egui. Add it with
+/// `EguiBundle::with_profiler`.
+///
+/// The egui timings and counts are those of the previous frame, since the current frame is only
+/// rendered after the overlay is built.
+#[derive(Clone, Debug)]
+pub struct EguiProfiler {
+    history_len: usize,
+    histogram_buckets: usize,
+}
+
+impl Default for EguiProfiler {
+    fn default() -> Self {
+        Self {
+            history_len: 300,
+            histogram_buckets: 20,
+        }
+    }
+}
+
+impl EguiProfiler {
+    /// Sets the number of frames shown in the graph and histogram. Defaults to 300.
+    pub fn with_history_len(mut self, frames: usize) -> Self {
+        self.history_len = frames.max(1);
+        self
+    }
+
+    /// Sets the number of buckets of the frame time histogram. Defaults to 20.
+    pub fn with_histogram_buckets(mut self, buckets: usize) -> Self {
+        self.histogram_buckets = buckets.max(1);
+        self
+    }
+}
+
+/// Frame time and egui time of one frame, in milliseconds.
+#[derive(Clone, Copy, Debug)]
+struct FrameSample {
+    frame_time: f32,
+    egui_time: f32,
+}
+
+fn millis(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+#[derive(Debug)]
+struct ProfilerState {
+    profiler: EguiProfiler,
+    samples: VecDeque<FrameSample>,
+}
+
+impl ProfilerState {
+    fn record<K>(&mut self, time: &Time, stats: &EguiRenderStats<K>) {
+        while self.samples.len() >= self.profiler.history_len {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(FrameSample {
+            frame_time: millis(time.delta_real_time()),
+            egui_time: millis(
+                stats.begin_frame_time
+                    + stats.end_frame_time
+                    + stats.tessellate_time
+                    + stats.upload_time,
+            ),
+        });
+    }
+
+    fn max_frame_time(&self) -> f32 {
+        self.samples
+            .iter()
+            .map(|sample| sample.frame_time)
+            .fold(0.0, f32::max)
+    }
+
+    fn show<K>(&self, ui: &mut Ui, stats: &EguiRenderStats<K>) {
+        let count = self.samples.len().max(1) as f32;
+        let average = self.samples.iter().map(|sample| sample.frame_time).sum::<f32>() / count;
+        let last = self.samples.back().map_or(0.0, |sample| sample.frame_time);
+
+        ui.label(format!(
+            "Frame: {:.2} ms (avg {:.2} ms, max {:.2} ms, {:.0} FPS)",
+            last,
+            average,
+            self.max_frame_time(),
+            if average > 0.0 { 1000.0 / average } else { 0.0 }
+        ));
+        ui.label(format!(
+            "Egui: begin {:.3} ms, end {:.3} ms, tessellate {:.3} ms, upload {:.3} ms",
+            millis(stats.begin_frame_time),
+            millis(stats.end_frame_time),
+            millis(stats.tessellate_time),
+            millis(stats.upload_time),
+        ));
+        ui.label(format!(
+            "{} meshes, {} draws, {} vertices, {} indices, {} textures",
+            stats.meshes, stats.draws, stats.vertices, stats.indices, stats.textures,
+        ));
+        ui.label(format!(
+            "{} bytes uploaded, {} atlas uploads",
+            stats.uploaded_bytes, stats.atlas_uploads,
+        ));
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.colored_label(FRAME_TIME_COLOR, "Frame time");
+            ui.colored_label(EGUI_TIME_COLOR, "Egui time");
+        });
+        self.graph(ui);
+
+        ui.separator();
+        ui.label("Frame time histogram");
+        self.histogram(ui);
+    }
+
+    fn graph(&self, ui: &mut Ui) {
+        let size = Vec2::new(ui.available_width(), 80.0);
+        let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::from_black_alpha(100));
+
+        let max = self.max_frame_time().max(1.0);
+        let step = rect.width() / (self.profiler.history_len.max(2) - 1) as f32;
+        let points = |value: fn(&FrameSample) -> f32| {
+            self.samples
+                .iter()
+                .enumerate()
+                .map(|(i, sample)| {
+                    Pos2::new(
+                        rect.left() + i as f32 * step,
+                        rect.bottom() - value(sample) / max * rect.height(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        painter.add(Shape::line(
+            points(|sample| sample.frame_time),
+            Stroke::new(1.0, FRAME_TIME_COLOR),
+        ));
+        painter.add(Shape::line(
+            points(|sample| sample.egui_time),
+            Stroke::new(1.0, EGUI_TIME_COLOR),
+        ));
+        painter.text(
+            rect.left_top(),
+            egui::Align2::LEFT_TOP,
+            format!("{:.1} ms", max),
+            egui::TextStyle::Small,
+            Color32::WHITE,
+        );
+    }
+
+    fn histogram(&self, ui: &mut Ui) {
+        let size = Vec2::new(ui.available_width(), 60.0);
+        let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::from_black_alpha(100));
+
+        let buckets = self.profiler.histogram_buckets;
+        let max = self.max_frame_time().max(1.0);
+        let mut counts = vec![0usize; buckets];
+        for sample in &self.samples {
+            let bucket = ((sample.frame_time / max) * buckets as f32) as usize;
+            counts[bucket.min(buckets - 1)] += 1;
+        }
+
+        let max_count = counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+        let width = rect.width() / buckets as f32;
+        for (i, count) in counts.into_iter().enumerate() {
+            let height = count as f32 / max_count * rect.height();
+            let bar = Rect::from_min_max(
+                Pos2::new(rect.left() + i as f32 * width, rect.bottom() - height),
+                Pos2::new(rect.left() + (i + 1) as f32 * width - 1.0, rect.bottom()),
+            );
+            painter.rect_filled(bar, 0.0, FRAME_TIME_COLOR);
+        }
+        painter.text(
+            rect.right_top(),
+            egui::Align2::RIGHT_TOP,
+            format!("0 - {:.1} ms", max),
+            egui::TextStyle::Small,
+            Color32::WHITE,
+        );
+    }
+}
+
+/// Shows an `EguiProfiler` with the egui context `K`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub(crate) struct ProfilerSystem<K> {
+    profiler: EguiProfiler,
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<K>,
+}
+
+impl<K> ProfilerSystem<K> {
+    pub fn new(profiler: EguiProfiler) -> Self {
+        Self {
+            profiler,
+            _key: PhantomData,
+        }
+    }
+}
+
+impl<K: EguiKey> System for ProfilerSystem<K> {
+    fn build(self) -> Box<dyn ParallelRunnable + 'static> {
+        let mut state = ProfilerState {
+            samples: VecDeque::with_capacity(self.profiler.history_len),
+            profiler: self.profiler,
+        };
+
+        Box::new(
+            SystemBuilder::new(format!("ProfilerSystem<{}>", std::any::type_name::<K>()))
+                .read_resource::<Time>()
+                .read_resource::<EguiContext<K>>()
+                .read_resource::<EguiRenderStats<K>>()
+                .build(move |_commands, _world, resources, _queries| {
+                    let (time, egui_ctx, stats) = resources;
+
+                    state.record(time, stats);
+
+                    egui_ctx.with_ui(|ctx| {
+                        egui::Window::new("Profiler")
+                            .default_width(400.0)
+                            .show(ctx, |ui| state.show(ui, stats));
+                    });
+                }),
+        )
+    }
+}
@@ -0,0 +1,117 @@
+use derivative::Derivative;
+
+/// A sink for the widget descriptions that egui reports for screen readers.
+///
+/// Insert an `EguiSpeech` with your own backend into the resources to replace text-to-speech,
+/// e.g. with a stub that records the utterances in tests.
+pub trait SpeechBackend: Send + Sync + 'static {
+    /// Speaks `text`. If `interrupt` is set, any ongoing speech is stopped first, otherwise `text`
+    /// is queued.
+    fn speak(&mut self, text: &str, interrupt: bool);
+
+    /// Sets the speech rate relative to the backend's normal rate, e.g. `2.0` is twice as fast.
+    fn set_rate(&mut self, rate: f32);
+}
+
+/// Resource holding the `SpeechBackend` used by `EguiSystem`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct EguiSpeech {
+    #[derivative(Debug = "ignore")]
+    backend: Box<dyn SpeechBackend>,
+    rate: Option<f32>,
+}
+
+impl EguiSpeech {
+    pub fn new(backend: impl SpeechBackend) -> Self {
+        Self {
+            backend: Box::new(backend),
+            rate: None,
+        }
+    }
+
+    pub fn speak(&mut self, text: &str, interrupt: bool) {
+        self.backend.speak(text, interrupt)
+    }
+
+    /// Sets the speech rate. The backend is only updated if the rate actually changed.
+    pub fn set_rate(&mut self, rate: f32) {
+        if self.rate != Some(rate) {
+            self.backend.set_rate(rate);
+            self.rate = Some(rate);
+        }
+    }
+}
+
+impl Default for EguiSpeech {
+    /// Uses the `tts` crate if the `tts` feature is enabled, and discards all speech otherwise.
+    fn default() -> Self {
+        #[cfg(feature = "tts")]
+        if let Some(tts) = TtsSpeech::new() {
+            return Self::new(tts);
+        }
+
+        Self::new(NoSpeech)
+    }
+}
+
+/// A speech backend that discards everything.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoSpeech;
+
+impl SpeechBackend for NoSpeech {
+    fn speak(&mut self, _text: &str, _interrupt: bool) {}
+
+    fn set_rate(&mut self, _rate: f32) {}
+}
+
+#[cfg(feature = "tts")]
+pub use self::tts_backend::TtsSpeech;
+
+#[cfg(feature = "tts")]
+mod tts_backend {
+    use derivative::Derivative;
+    use tts::Tts;
+
+    use super::SpeechBackend;
+
+    /// Text-to-speech through the platform's speech synthesizer.
+    ///
+    /// The `tts` crate doesn't support selecting a voice, so the system's default voice is used.
+    #[derive(Derivative)]
+    #[derivative(Debug)]
+    pub struct TtsSpeech {
+        #[derivative(Debug = "ignore")]
+        tts: Tts,
+    }
+
+    impl TtsSpeech {
+        /// Returns `None` if no speech synthesizer is available.
+        pub fn new() -> Option<Self> {
+            match Tts::default() {
+                Ok(tts) => Some(Self { tts }),
+                Err(e) => {
+                    log::error!("Failed to initialize text-to-speech: {}", e);
+                    None
+                }
+            }
+        }
+    }
+
+    impl SpeechBackend for TtsSpeech {
+        fn speak(&mut self, text: &str, interrupt: bool) {
+            if let Err(e) = self.tts.speak(text, interrupt) {
+                log::error!("Failed to speak: {}", e);
+            }
+        }
+
+        fn set_rate(&mut self, rate: f32) {
+            let rate = (self.tts.normal_rate() * rate)
+                .max(self.tts.min_rate())
+                .min(self.tts.max_rate());
+            if let Err(e) = self.tts.set_rate(rate) {
+                log::error!("Failed to set speech rate: {}", e);
+            }
+        }
+    }
+}
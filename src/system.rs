@@ -1,7 +1,25 @@
 use std::{
-    collections::HashMap,
-    time::Duration,
+    collections::{
+        hash_map::DefaultHasher,
+        HashMap,
+    },
     fmt,
+    hash::{
+        Hash,
+        Hasher,
+    },
+    marker::PhantomData,
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Mutex,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 use amethyst_assets::Handle;
@@ -15,10 +33,7 @@ use amethyst_core::{
     EventChannel,
     Time,
 };
-use amethyst_input::{
-    ScrollDirection,
-    VirtualKeyCode,
-};
+use amethyst_input::VirtualKeyCode;
 use amethyst_rendy::Texture;
 use amethyst_window::{
     ScreenDimensions,
@@ -26,9 +41,11 @@ use amethyst_window::{
 };
 use derivative::Derivative;
 use egui::{
+    output::OutputEvent,
     CtxRef,
     CursorIcon,
     Event,
+    Id,
     Key,
     Modifiers,
     Output,
@@ -36,8 +53,16 @@ use egui::{
     Pos2,
     RawInput,
     Rect,
+    TextureId,
+    TouchDeviceId,
+    TouchId,
+    TouchPhase,
     Vec2,
 };
+use serde::{
+    Deserialize,
+    Serialize,
+};
 use winit::{
     dpi::PhysicalPosition,
     event::{
@@ -47,15 +72,53 @@ use winit::{
         ModifiersState,
         MouseButton,
         MouseScrollDelta,
+        Touch,
+        TouchPhase as WTouchPhase,
         WindowEvent,
     },
     window::CursorIcon as WCursorIcon,
 };
 
-#[derive(Debug, Default)]
-pub struct EguiInputGrab {
+use crate::{
+    clipboard::EguiClipboard,
+    pass::EguiRenderStats,
+    speech::EguiSpeech,
+};
+
+/// Identifies an egui context. Each key type has its own `EguiContext<K>`, `EguiInputGrab<K>`,
+/// `EguiSystem<K>` and `RenderEgui<K>`, so that several UIs can be used at the same time.
+pub trait EguiKey: fmt::Debug + Send + Sync + 'static {}
+
+/// Key of the default egui context.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultEgui;
+
+impl EguiKey for DefaultEgui {}
+
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub struct EguiInputGrab<K = DefaultEgui> {
     pub keyboard: bool,
     pub mouse: bool,
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<K>,
+}
+
+/// Which input an egui context receives.
+#[derive(Clone, Debug, Derivative)]
+#[derivative(Default)]
+pub struct EguiInputRouting {
+    /// Receive keyboard and text input.
+    #[derivative(Default(value = "true"))]
+    pub keyboard: bool,
+
+    /// Receive mouse input.
+    #[derivative(Default(value = "true"))]
+    pub pointer: bool,
+
+    /// Set the mouse cursor icon. Only one context should do this.
+    #[derivative(Default(value = "true"))]
+    pub cursor_icon: bool,
 }
 
 pub(crate) enum EguiStage {
@@ -75,27 +138,54 @@ impl fmt::Debug for EguiStage {
 }
 
 #[derive(Derivative)]
-#[derivative(Debug)]
-pub struct EguiContext {
+#[derivative(Debug(bound = ""))]
+pub struct EguiContext<K = DefaultEgui> {
     #[derivative(Debug = "ignore")]
     pub(crate) ctx: CtxRef,
     pub(crate) stage: EguiStage,
     #[derivative(Debug = "ignore")]
     pub(crate) user_textures: HashMap<u64, Handle<Texture>>,
+    next_user_texture_id: u64,
+    pub input_routing: EguiInputRouting,
+    pub(crate) offscreen_size: Option<(u32, u32)>,
+    #[derivative(Debug = "ignore")]
+    injected_events: Vec<Event>,
+    injected_pointer_pos: Pos2,
+    ime_position: Mutex<Option<Pos2>>,
+    /// Keys handled outside of egui, e.g. the console's toggle key, and the text edit that has
+    /// them. See `EguiSystem::is_hotkey`.
+    pub(crate) hotkeys: Vec<(VirtualKeyCode, Id)>,
+    /// Hotkeys pressed in this frame.
+    pub(crate) pressed_hotkeys: Vec<VirtualKeyCode>,
+    /// Whether `with_ui` already warned about being used outside of a frame.
+    warned_outside_frame: AtomicBool,
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<K>,
 }
 
-
-impl Default for EguiContext {
+impl<K> Default for EguiContext<K> {
     fn default() -> Self {
         Self {
             ctx: CtxRef::default(),
             stage: EguiStage::Begin,
             user_textures: HashMap::new(),
+            next_user_texture_id: 0,
+            input_routing: EguiInputRouting::default(),
+            offscreen_size: None,
+            injected_events: Vec::new(),
+            injected_pointer_pos: Pos2::ZERO,
+            ime_position: Mutex::new(None),
+            hotkeys: Vec::new(),
+            pressed_hotkeys: Vec::new(),
+            warned_outside_frame: AtomicBool::new(false),
+            _key: PhantomData,
         }
     }
 }
 
-impl EguiContext {
+impl<K> EguiContext<K> {
+    /// Returns the egui context, if a frame is currently being built. This is only the case
+    /// between `EguiSystem` and the render pass, see `EguiBundle::with_ui_system`.
     pub fn ctx(&self) -> Option<&CtxRef> {
         if matches!(self.stage, EguiStage::Render) {
             Some(&self.ctx)
@@ -104,53 +194,287 @@ impl EguiContext {
             None
         }
     }
+
+    /// Calls `f` with the egui context, if a frame is currently being built. Otherwise returns
+    /// `None` and logs a warning the first time.
+    pub fn with_ui<R>(&self, f: impl FnOnce(&CtxRef) -> R) -> Option<R> {
+        if let Some(ctx) = self.ctx() {
+            Some(f(ctx))
+        }
+        else {
+            if !self.warned_outside_frame.swap(true, Ordering::Relaxed) {
+                log::warn!(
+                    "EguiContext used outside of a frame ({:?}). Add UI systems with \
+                     `EguiBundle::with_ui_system`.",
+                    self.stage
+                );
+            }
+            None
+        }
+    }
+
+    /// Returns the size in pixels of the image this context is rendered into, or `None` if it is
+    /// rendered on screen. See `EguiBundle::with_offscreen_size`.
+    pub fn offscreen_size(&self) -> Option<(u32, u32)> {
+        self.offscreen_size
+    }
+
+    /// Queues an input event for the next frame.
+    pub fn inject_event(&mut self, event: Event) {
+        self.injected_events.push(event);
+    }
+
+    /// Moves the pointer to the texture coordinates `uv` (from `0.0` to `1.0`), e.g. where a ray
+    /// cast hits an in-world panel.
+    pub fn inject_pointer_uv(&mut self, uv: Vec2) {
+        // Egui's screen rect is only known after the first frame, but offscreen contexts always
+        // have the size of their image.
+        let size = match self.offscreen_size {
+            Some((width, height)) => Vec2::new(width as f32, height as f32),
+            None => self.ctx.input().screen_rect().size(),
+        };
+        let pos = Pos2::new(uv.x * size.x, uv.y * size.y);
+        self.injected_pointer_pos = pos;
+        self.inject_event(Event::PointerMoved(pos));
+    }
+
+    /// Presses or releases a pointer button at the position of the last `inject_pointer_uv`.
+    pub fn inject_pointer_button(&mut self, button: PointerButton, pressed: bool) {
+        self.inject_event(Event::PointerButton {
+            pos: self.injected_pointer_pos,
+            button,
+            pressed,
+            modifiers: Modifiers::default(),
+        });
+    }
+
+    /// Removes the pointer, e.g. when the ray cast doesn't hit the panel anymore.
+    pub fn inject_pointer_gone(&mut self) {
+        self.inject_event(Event::PointerGone);
+    }
+
+    /// Places the input method's candidate window at `pos` (in points), e.g. below the text edit
+    /// that has the keyboard focus:
+    ///
+    /// ```ignore
+    /// let response = ui.text_edit_singleline(&mut text);
+    /// if response.has_focus() {
+    ///     egui_ctx.set_ime_position(response.rect.left_bottom());
+    /// }
+    /// ```
+    ///
+    /// This egui version doesn't report the position of the text cursor, so it has to be set by
+    /// the UI. winit doesn't report the text being composed either, so it is only shown in the
+    /// input method's own window. The composed text arrives as normal text input.
+    pub fn set_ime_position(&self, pos: Pos2) {
+        *self.ime_position.lock().unwrap_or_else(|e| e.into_inner()) = Some(pos);
+    }
+
+    /// Takes the IME position set during the last frame.
+    fn take_ime_position(&mut self) -> Option<Pos2> {
+        self.ime_position
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    }
+
+    /// Registers a texture, so that it can be used with e.g. `egui::Image`. Returns the
+    /// `TextureId` that refers to the texture.
+    pub fn register_texture(&mut self, texture: Handle<Texture>) -> TextureId {
+        let id = self.next_user_texture_id;
+        self.next_user_texture_id += 1;
+        self.user_textures.insert(id, texture);
+        TextureId::User(id)
+    }
+
+    /// Replaces the texture for an already registered `TextureId`. Returns the previous texture,
+    /// or `None` if the `TextureId` wasn't registered, in which case nothing is changed.
+    pub fn replace_texture(
+        &mut self,
+        texture_id: TextureId,
+        texture: Handle<Texture>,
+    ) -> Option<Handle<Texture>> {
+        match texture_id {
+            TextureId::User(id) => {
+                self.user_textures
+                    .get_mut(&id)
+                    .map(|old| std::mem::replace(old, texture))
+            }
+            TextureId::Egui => None,
+        }
+    }
+
+    /// Unregisters a texture. Returns the texture, if it was registered.
+    pub fn unregister_texture(&mut self, texture_id: TextureId) -> Option<Handle<Texture>> {
+        match texture_id {
+            TextureId::User(id) => self.user_textures.remove(&id),
+            TextureId::Egui => None,
+        }
+    }
+
+    /// Returns the texture registered with `texture_id`.
+    pub fn texture(&self, texture_id: TextureId) -> Option<&Handle<Texture>> {
+        match texture_id {
+            TextureId::User(id) => self.user_textures.get(&id),
+            TextureId::Egui => None,
+        }
+    }
+
+    /// Iterates over all registered textures.
+    pub fn textures(&self) -> impl Iterator<Item = (TextureId, &Handle<Texture>)> {
+        self.user_textures
+            .iter()
+            .map(|(id, texture)| (TextureId::User(*id), texture))
+    }
 }
 
-#[derive(Clone, Derivative)]
+/// Options shared by all egui contexts.
+///
+/// It can be loaded from a RON file with `EguiBundle::from_config_path`. Missing fields keep their
+/// default values.
+#[derive(Clone, Debug, Derivative, Serialize, Deserialize)]
 #[derivative(Default)]
+#[serde(default)]
 pub struct EguiConfig {
+    /// Factor applied to all scroll deltas.
     #[derivative(Default(value = "1.0"))]
     pub scroll_sensitivity: f32,
 
+    /// Points scrolled per line, for mouse wheels that scroll by lines instead of pixels.
+    #[derivative(Default(value = "50.0"))]
+    pub scroll_points_per_line: f32,
+
+    /// Inverts horizontal scrolling.
+    pub invert_scroll_x: bool,
+
+    /// Inverts vertical scrolling.
+    pub invert_scroll_y: bool,
+
     pub mirror_mouse_buttons: bool,
 
     #[cfg(feature = "webbrowser")]
     pub allow_webbrowser: bool,
 
-    #[cfg(feature = "clipboard")]
+    /// Enables copy/cut/paste through the `EguiClipboard` resource.
     pub allow_clipboard: bool,
 
-    #[cfg(feature = "tts")]
+    /// Speaks descriptions of the widgets the user interacts with through the `EguiSpeech`
+    /// resource.
     pub enable_tts: bool,
+
+    /// Speech rate relative to the normal rate of the speech synthesizer.
+    #[derivative(Default(value = "1.0"))]
+    pub tts_rate: f32,
+
+    /// Whether new descriptions interrupt the ongoing speech. Otherwise they are queued.
+    #[derivative(Default(value = "true"))]
+    pub tts_interrupt: bool,
+
+    /// Scales the UI on top of the monitor's scale factor. It is clamped between `MIN_UI_SCALE`
+    /// and `MAX_UI_SCALE`.
+    ///
+    /// Changing the scale rebuilds egui's font atlas, so a new scale only takes effect once it
+    /// stayed the same for a moment, e.g. when the user stopped dragging a slider. Offscreen
+    /// contexts aren't scaled.
+    #[derivative(Default(value = "1.0"))]
+    pub ui_scale: f32,
 }
 
+pub const MIN_UI_SCALE: f32 = 0.25;
+pub const MAX_UI_SCALE: f32 = 4.0;
+
+/// The UI scale is rounded to multiples of this, so that tiny changes don't rebuild the font atlas.
+const UI_SCALE_STEP: f32 = 0.05;
+
+/// How long a new UI scale must stay the same before it is applied.
+const UI_SCALE_DELAY: Duration = Duration::from_millis(200);
+
 #[derive(Derivative)]
-pub struct EguiSystem {
+pub struct EguiSystem<K = DefaultEgui> {
     winit_event_reader: ReaderId<WEvent<'static, ()>>,
     current_mouse_pos: Pos2,
     current_modifiers: Modifiers,
+    touches: TouchState,
+    pixels_per_point: f32,
+    ui_scale: f32,
+    /// A new UI scale and how long it stayed the same.
+    pending_ui_scale: Option<(f32, Duration)>,
+    /// The IME position last set on the window.
+    ime_position: Option<Pos2>,
+    /// Drops the character typed with a hotkey.
+    suppress_char: bool,
+    _key: PhantomData<K>,
 }
 
-impl EguiSystem {
+impl<K: EguiKey> EguiSystem<K> {
     pub fn new(winit_event_reader: ReaderId<WEvent<'_, ()>>) -> Self {
         Self {
             winit_event_reader,
             current_mouse_pos: Pos2::default(),
             current_modifiers: Modifiers::default(),
+            touches: TouchState::default(),
+            pixels_per_point: 1.0,
+            ui_scale: 1.0,
+            pending_ui_scale: None,
+            ime_position: None,
+            suppress_char: false,
+            _key: PhantomData,
         }
     }
 
+    /// Returns whether `input` is one of the `hotkeys`. Hotkeys are only handled, if no widget or
+    /// the hotkey's own text edit has the keyboard focus, so that they can still be typed into
+    /// other text edits.
+    fn is_hotkey(input: &KeyboardInput, hotkeys: &[(VirtualKeyCode, Id)], ctx: &CtxRef) -> bool {
+        input.virtual_keycode.map_or(false, |key| {
+            hotkeys.iter().any(|(hotkey, owner)| {
+                *hotkey == key && (!ctx.wants_keyboard_input() || ctx.memory().has_focus(*owner))
+            })
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn handle_window_events(
         &mut self,
         egui_input: &mut RawInput,
         window_events: &EventChannel<WEvent<'static, ()>>,
         config: &EguiConfig,
+        routing: &EguiInputRouting,
+        clipboard: &mut EguiClipboard,
+        ctx: &CtxRef,
+        hotkeys: &[(VirtualKeyCode, Id)],
+        pressed_hotkeys: &mut Vec<VirtualKeyCode>,
     ) {
         for event in window_events.read(&mut self.winit_event_reader) {
             match event {
                 WEvent::WindowEvent { event, .. } => {
                     match event {
+                        WindowEvent::KeyboardInput { .. } | WindowEvent::ReceivedCharacter(_)
+                            if !routing.keyboard => {}
+                        WindowEvent::CursorMoved { .. }
+                        | WindowEvent::MouseInput { .. }
+                        | WindowEvent::CursorLeft { .. }
+                        | WindowEvent::MouseWheel { .. }
+                        | WindowEvent::Touch(_)
+                            if !routing.pointer => {}
+                        WindowEvent::KeyboardInput { input, .. }
+                            if Self::is_hotkey(input, hotkeys, ctx) =>
+                        {
+                            // The hotkey is neither passed to egui, nor typed.
+                            if let (ElementState::Pressed, Some(key)) =
+                                (input.state, input.virtual_keycode)
+                            {
+                                pressed_hotkeys.push(key);
+                                self.suppress_char = true;
+                            }
+                        }
                         WindowEvent::KeyboardInput { input, .. } => {
+                            if matches!(input.state, ElementState::Pressed) {
+                                self.suppress_char = false;
+                            }
+                            if config.allow_clipboard {
+                                egui_input.clipboard_shortcut(input, clipboard);
+                            }
                             egui_input.key(input);
                         }
                         WindowEvent::ModifiersChanged(modifiers) => {
@@ -158,10 +482,16 @@ impl EguiSystem {
                             self.current_modifiers = egui_input.modifiers;
                         }
                         WindowEvent::ReceivedCharacter(chr) => {
-                            egui_input.key_char(*chr);
+                            if !std::mem::take(&mut self.suppress_char) {
+                                egui_input.key_char(*chr);
+                            }
                         }
                         WindowEvent::CursorMoved { position, .. } => {
-                            egui_input.mouse_moved(position, &mut self.current_mouse_pos);
+                            egui_input.mouse_moved(
+                                position,
+                                self.pixels_per_point,
+                                &mut self.current_mouse_pos,
+                            );
                         }
                         WindowEvent::MouseInput { state, button, .. } => {
                             egui_input.mouse_button(
@@ -175,10 +505,16 @@ impl EguiSystem {
                             egui_input.mouse_left();
                         }
                         WindowEvent::MouseWheel { delta, .. } => {
-                            egui_input.mouse_wheel(delta);
+                            egui_input.mouse_wheel(delta, self.pixels_per_point, config);
                         }
-                        WindowEvent::ScaleFactorChanged { .. } => {
-                            //todo!()
+                        WindowEvent::Touch(touch) => {
+                            self.touches.touch(
+                                egui_input,
+                                touch,
+                                self.pixels_per_point,
+                                config,
+                                &mut self.current_mouse_pos,
+                            );
                         }
                         _ => {}
                     }
@@ -188,9 +524,53 @@ impl EguiSystem {
         }
     }
 
-    #[allow(unused_variables)]
-    fn handle_output(&mut self, egui_output: Output, window: &Window, config: &EguiConfig) {
-        set_cursor(window, egui_output.cursor_icon);
+    /// Applies `EguiConfig::ui_scale` once it stayed the same for `UI_SCALE_DELAY`.
+    fn update_ui_scale(&mut self, config: &EguiConfig, delta: Duration) {
+        let ui_scale = config.ui_scale.max(MIN_UI_SCALE).min(MAX_UI_SCALE);
+        let ui_scale = (ui_scale / UI_SCALE_STEP).round() * UI_SCALE_STEP;
+
+        if ui_scale == self.ui_scale {
+            self.pending_ui_scale = None;
+            return;
+        }
+
+        let stable_for = match self.pending_ui_scale {
+            Some((pending, stable_for)) if pending == ui_scale => stable_for + delta,
+            _ => Duration::default(),
+        };
+        if stable_for >= UI_SCALE_DELAY {
+            log::debug!("UI scale changed: {} -> {}", self.ui_scale, ui_scale);
+            self.ui_scale = ui_scale;
+            self.pending_ui_scale = None;
+        }
+        else {
+            self.pending_ui_scale = Some((ui_scale, stable_for));
+        }
+    }
+
+    fn handle_output(
+        &mut self,
+        egui_output: Output,
+        window: &Window,
+        config: &EguiConfig,
+        routing: &EguiInputRouting,
+        clipboard: &mut EguiClipboard,
+        speech: &mut EguiSpeech,
+        ime_position: Option<Pos2>,
+    ) {
+        if routing.cursor_icon {
+            set_cursor(window, egui_output.cursor_icon);
+        }
+
+        if let Some(pos) = ime_position {
+            if routing.keyboard && self.ime_position != Some(pos) {
+                window.set_ime_position(PhysicalPosition::new(
+                    pos.x * self.pixels_per_point,
+                    pos.y * self.pixels_per_point,
+                ));
+                self.ime_position = Some(pos);
+            }
+        }
 
         #[cfg(feature = "webbrowser")]
         if config.allow_webbrowser {
@@ -202,54 +582,58 @@ impl EguiSystem {
             }
         }
 
-        #[cfg(feature = "clipboard")]
-        if config.allow_clipboard {
-            if !egui_output.copied_text.is_empty() {
-                todo!()
-            }
+        if config.allow_clipboard && !egui_output.copied_text.is_empty() {
+            clipboard.set_contents(egui_output.copied_text);
         }
 
         // TODO: handle `needs_repaint`?
 
-        #[cfg(feature = "tts")]
         if config.enable_tts {
-            use egui::output::OutputEvent;
-            for event in egui_output.events {
-                match event {
-                    OutputEvent::Clicked(i)
-                    | OutputEvent::DoubleClicked(i)
-                    | OutputEvent::FocusGained(i)
-                    | OutputEvent::TextSelectionChanged(i)
-                    | OutputEvent::ValueChanged(i) => {
-                        let _text = i.description();
-                        todo!()
-                    }
-                }
+            speak_output_events(egui_output.events, config, speech);
+        }
+    }
+}
+
+/// Speaks the descriptions of the widgets in `events`.
+fn speak_output_events(events: Vec<OutputEvent>, config: &EguiConfig, speech: &mut EguiSpeech) {
+    speech.set_rate(config.tts_rate);
+
+    // Only the first description of a frame may interrupt, so that the following ones don't cut it
+    // off.
+    let mut interrupt = config.tts_interrupt;
+    for event in events {
+        match event {
+            OutputEvent::Clicked(i)
+            | OutputEvent::DoubleClicked(i)
+            | OutputEvent::FocusGained(i)
+            | OutputEvent::TextSelectionChanged(i)
+            | OutputEvent::ValueChanged(i) => {
+                speech.speak(&i.description(), interrupt);
+                interrupt = false;
             }
         }
     }
 }
 
-impl System for EguiSystem {
+impl<K: EguiKey> System for EguiSystem<K> {
     fn build(mut self) -> Box<dyn ParallelRunnable + 'static> {
         Box::new(
-            SystemBuilder::new("EguiSystem")
+            SystemBuilder::new(format!("EguiSystem<{}>", std::any::type_name::<K>()))
                 .read_resource::<EguiConfig>()
-                .write_resource::<EguiContext>()
+                .write_resource::<EguiContext<K>>()
                 .read_resource::<ScreenDimensions>()
                 .read_resource::<EventChannel<WEvent<'_, ()>>>()
                 .read_resource::<Time>()
                 .read_resource::<Window>()
-                .write_resource::<EguiInputGrab>()
+                .write_resource::<EguiInputGrab<K>>()
+                .write_resource::<EguiClipboard>()
+                .write_resource::<EguiSpeech>()
+                .write_resource::<EguiRenderStats<K>>()
                 //.with_query(<(Read<Transform>, Read<ChunkLoadTag>, TryRead<Camera>)>::query())
                 .build(move |_commands, _world, resources, _queries| {
                     // # TODO
                     //
-                    // - Fill field `pixels_per_point`. See [1]
                     // - Should we use `FpsCounter` for `predicted_dt`?
-                    // - Allow events to be sent to this system? E.g. Copy/Cut?
-                    //
-                    // [1] https://docs.rs/winit/0.25.0/winit/dpi/index.html
 
                     let (
                         config,
@@ -259,6 +643,9 @@ impl System for EguiSystem {
                         time,
                         window,
                         input_grab,
+                        clipboard,
+                        speech,
+                        stats,
                     ) = resources;
 
                     if matches!(&egui_ctx.stage, EguiStage::Render) {
@@ -270,7 +657,19 @@ impl System for EguiSystem {
                     if let EguiStage::End(egui_output) =
                         std::mem::replace(&mut egui_ctx.stage, EguiStage::Render)
                     {
-                        self.handle_output(egui_output, window, config);
+                        // Offscreen contexts have no text cursor on the window.
+                        let ime_position = egui_ctx
+                            .take_ime_position()
+                            .filter(|_| egui_ctx.offscreen_size.is_none());
+                        self.handle_output(
+                            egui_output,
+                            window,
+                            config,
+                            &egui_ctx.input_routing,
+                            clipboard,
+                            speech,
+                            ime_position,
+                        );
                     }
 
                     // Set `EguiInputGrab` resource for other systems to know, whether Egui is using
@@ -281,23 +680,56 @@ impl System for EguiSystem {
 
                     // Gather inputs
 
-                    let screen_rect = Rect::from_min_size(
-                        Pos2::ZERO,
-                        Vec2::new(screen_dimensions.width(), screen_dimensions.height()),
-                    );
+                    // Egui works in logical points, while the window reports physical pixels.
+                    // See https://docs.rs/winit/0.25.0/winit/dpi/index.html
+                    // The UI scale is part of `pixels_per_point`, so that it is applied to input
+                    // coordinates, the screen size and the projection alike.
+                    self.update_ui_scale(config, time.delta_real_time());
+                    self.pixels_per_point =
+                        screen_dimensions.hidpi_factor() as f32 * self.ui_scale;
 
                     let mut egui_input = RawInput {
-                        screen_rect: Some(screen_rect),
                         modifiers: self.current_modifiers,
                         time: Some(duration_as_secs_with_nanos_f64(time.absolute_real_time())),
                         predicted_dt: duration_as_secs_with_nanos_f32(time.delta_real_time()),
                         ..RawInput::default()
                     };
 
-                    self.handle_window_events(&mut egui_input, &winit_events, &config);
+                    let mut pressed_hotkeys = std::mem::take(&mut egui_ctx.pressed_hotkeys);
+                    pressed_hotkeys.clear();
+                    self.handle_window_events(
+                        &mut egui_input,
+                        &winit_events,
+                        &config,
+                        &egui_ctx.input_routing,
+                        clipboard,
+                        &egui_ctx.ctx,
+                        &egui_ctx.hotkeys,
+                        &mut pressed_hotkeys,
+                    );
+                    egui_ctx.pressed_hotkeys = pressed_hotkeys;
+
+                    egui_input.events.append(&mut egui_ctx.injected_events);
+
+                    let (screen_size, pixels_per_point) =
+                        if let Some((width, height)) = egui_ctx.offscreen_size {
+                            // Offscreen contexts are rendered 1:1 into their image.
+                            (Vec2::new(width as f32, height as f32), 1.0)
+                        }
+                        else {
+                            (
+                                Vec2::new(screen_dimensions.width(), screen_dimensions.height())
+                                    / self.pixels_per_point,
+                                self.pixels_per_point,
+                            )
+                        };
+                    egui_input.screen_rect = Some(Rect::from_min_size(Pos2::ZERO, screen_size));
+                    egui_input.pixels_per_point = Some(pixels_per_point);
 
                     // Render UI
+                    let start = Instant::now();
                     egui_ctx.ctx.begin_frame(egui_input);
+                    stats.begin_frame_time = start.elapsed();
                     egui_ctx.stage = EguiStage::Render;
                 }),
         )
@@ -314,9 +746,19 @@ fn duration_as_secs_with_nanos_f32(duration: Duration) -> f32 {
 
 trait EguiInput {
     fn key(&mut self, keyboard_input: &KeyboardInput);
+    fn clipboard_shortcut(
+        &mut self,
+        keyboard_input: &KeyboardInput,
+        clipboard: &mut EguiClipboard,
+    );
     fn modifiers(&mut self, modifiers: &ModifiersState, current_modifiers: &mut Modifiers);
     fn key_char(&mut self, c: char);
-    fn mouse_moved(&mut self, position: &PhysicalPosition<f64>, current_mouse_pos: &mut Pos2);
+    fn mouse_moved(
+        &mut self,
+        position: &PhysicalPosition<f64>,
+        pixels_per_point: f32,
+        current_mouse_pos: &mut Pos2,
+    );
     fn mouse_button(
         &mut self,
         state: ElementState,
@@ -324,11 +766,16 @@ trait EguiInput {
         current_mouse_pos: &Pos2,
         mirror_buttons: bool,
     );
-    fn mouse_wheel(&mut self, delta: &MouseScrollDelta);
+    fn mouse_wheel(
+        &mut self,
+        delta: &MouseScrollDelta,
+        pixels_per_point: f32,
+        config: &EguiConfig,
+    );
     fn mouse_left(&mut self);
 
     //fn set_modifier(&mut self, key: VirtualKeyCode, value: bool);
-    fn add_scroll_delta(&mut self, direction: ScrollDirection, sensitivty: f32);
+    fn add_scroll_delta(&mut self, delta: Vec2, config: &EguiConfig);
 }
 
 impl EguiInput for RawInput {
@@ -346,6 +793,28 @@ impl EguiInput for RawInput {
         }
     }
 
+    fn clipboard_shortcut(
+        &mut self,
+        keyboard_input: &KeyboardInput,
+        clipboard: &mut EguiClipboard,
+    ) {
+        if !self.modifiers.command || !matches!(keyboard_input.state, ElementState::Pressed) {
+            return;
+        }
+
+        match keyboard_input.virtual_keycode {
+            Some(VirtualKeyCode::C) => self.events.push(Event::Copy),
+            Some(VirtualKeyCode::X) => self.events.push(Event::Cut),
+            Some(VirtualKeyCode::V) => {
+                // Egui has no dedicated paste event, pasted text is just text input.
+                if let Some(contents) = clipboard.get_contents() {
+                    self.events.push(Event::Text(contents));
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn modifiers(&mut self, modifiers: &ModifiersState, current_modifiers: &mut Modifiers) {
         let modifiers = convert_modifiers(modifiers);
         self.modifiers = modifiers;
@@ -353,13 +822,24 @@ impl EguiInput for RawInput {
     }
 
     fn key_char(&mut self, c: char) {
-        if c != '\n' && c != '\r' {
+        // Text committed by an input method arrives here too. Control characters are handled as
+        // keys, and macOS sends function keys as characters from the private use area.
+        let is_private_use = ('\u{e000}'..='\u{f8ff}').contains(&c);
+        if !c.is_control() && !is_private_use {
             self.events.push(Event::Text(c.to_string()))
         }
     }
 
-    fn mouse_moved(&mut self, position: &PhysicalPosition<f64>, current_mouse_pos: &mut Pos2) {
-        let pos = Pos2::new(position.x as f32, position.y as f32);
+    fn mouse_moved(
+        &mut self,
+        position: &PhysicalPosition<f64>,
+        pixels_per_point: f32,
+        current_mouse_pos: &mut Pos2,
+    ) {
+        let pos = Pos2::new(
+            position.x as f32 / pixels_per_point,
+            position.y as f32 / pixels_per_point,
+        );
         self.events.push(Event::PointerMoved(pos));
         *current_mouse_pos = pos;
     }
@@ -381,81 +861,364 @@ impl EguiInput for RawInput {
         }
     }
 
-    fn mouse_wheel(&mut self, delta: &MouseScrollDelta) {
-        if let MouseScrollDelta::PixelDelta(px) = delta {
-            self.scroll_delta += Vec2::new(px.x as f32, px.y as f32);
-        }
+    fn mouse_wheel(
+        &mut self,
+        delta: &MouseScrollDelta,
+        pixels_per_point: f32,
+        config: &EguiConfig,
+    ) {
+        let delta = match delta {
+            MouseScrollDelta::LineDelta(x, y) => {
+                Vec2::new(*x, *y) * config.scroll_points_per_line
+            }
+            MouseScrollDelta::PixelDelta(px) => {
+                Vec2::new(px.x as f32, px.y as f32) / pixels_per_point
+            }
+        };
+        self.add_scroll_delta(delta, config);
     }
 
     fn mouse_left(&mut self) {
         self.events.push(Event::PointerGone);
     }
 
-    fn add_scroll_delta(&mut self, direction: ScrollDirection, sensitivty: f32) {
-        match direction {
-            ScrollDirection::ScrollUp => self.scroll_delta.y -= sensitivty,
-            ScrollDirection::ScrollDown => self.scroll_delta.y += sensitivty,
-            ScrollDirection::ScrollLeft => self.scroll_delta.x -= sensitivty,
-            ScrollDirection::ScrollRight => self.scroll_delta.x += sensitivty,
+    fn add_scroll_delta(&mut self, mut delta: Vec2, config: &EguiConfig) {
+        // Shift turns a vertical mouse wheel into a horizontal one.
+        if self.modifiers.shift && delta.x == 0.0 {
+            delta = Vec2::new(delta.y, 0.0);
+        }
+        if config.invert_scroll_x {
+            delta.x = -delta.x;
+        }
+        if config.invert_scroll_y {
+            delta.y = -delta.y;
         }
+        self.scroll_delta += delta * config.scroll_sensitivity;
     }
 }
 
-fn convert_key(key: &VirtualKeyCode) -> Option<Key> {
-    match key {
-        VirtualKeyCode::Down => Some(Key::ArrowDown),
-        VirtualKeyCode::Left => Some(Key::ArrowLeft),
-        VirtualKeyCode::Right => Some(Key::ArrowRight),
-        VirtualKeyCode::Up => Some(Key::ArrowUp),
-        VirtualKeyCode::Escape => Some(Key::Escape),
-        VirtualKeyCode::Tab => Some(Key::Tab),
-        VirtualKeyCode::Back => Some(Key::Backspace),
-        VirtualKeyCode::Return => Some(Key::Enter),
-        VirtualKeyCode::Space => Some(Key::Space),
-        VirtualKeyCode::Insert => Some(Key::Insert),
-        VirtualKeyCode::Delete => Some(Key::Delete),
-        VirtualKeyCode::Home => Some(Key::Home),
-        VirtualKeyCode::End => Some(Key::End),
-        VirtualKeyCode::PageUp => Some(Key::PageUp),
-        VirtualKeyCode::PageDown => Some(Key::PageDown),
-        VirtualKeyCode::Key0 => Some(Key::Num0),
-        VirtualKeyCode::Key1 => Some(Key::Num1),
-        VirtualKeyCode::Key2 => Some(Key::Num2),
-        VirtualKeyCode::Key3 => Some(Key::Num3),
-        VirtualKeyCode::Key4 => Some(Key::Num4),
-        VirtualKeyCode::Key5 => Some(Key::Num5),
-        VirtualKeyCode::Key6 => Some(Key::Num6),
-        VirtualKeyCode::Key7 => Some(Key::Num7),
-        VirtualKeyCode::Key8 => Some(Key::Num8),
-        VirtualKeyCode::Key9 => Some(Key::Num9),
-        VirtualKeyCode::A => Some(Key::A),
-        VirtualKeyCode::B => Some(Key::B),
-        VirtualKeyCode::C => Some(Key::C),
-        VirtualKeyCode::D => Some(Key::D),
-        VirtualKeyCode::E => Some(Key::E),
-        VirtualKeyCode::F => Some(Key::F),
-        VirtualKeyCode::G => Some(Key::G),
-        VirtualKeyCode::H => Some(Key::H),
-        VirtualKeyCode::I => Some(Key::I),
-        VirtualKeyCode::J => Some(Key::J),
-        VirtualKeyCode::K => Some(Key::K),
-        VirtualKeyCode::L => Some(Key::L),
-        VirtualKeyCode::M => Some(Key::M),
-        VirtualKeyCode::N => Some(Key::N),
-        VirtualKeyCode::O => Some(Key::O),
-        VirtualKeyCode::P => Some(Key::P),
-        VirtualKeyCode::Q => Some(Key::Q),
-        VirtualKeyCode::R => Some(Key::R),
-        VirtualKeyCode::S => Some(Key::S),
-        VirtualKeyCode::T => Some(Key::T),
-        VirtualKeyCode::U => Some(Key::U),
-        VirtualKeyCode::V => Some(Key::V),
-        VirtualKeyCode::W => Some(Key::W),
-        VirtualKeyCode::X => Some(Key::X),
-        VirtualKeyCode::Y => Some(Key::Y),
-        VirtualKeyCode::Z => Some(Key::Z),
-        _ => None,
+/// Where the pointer moves before a touch is released without clicking.
+const POINTER_AWAY: Pos2 = Pos2 {
+    x: -10_000.0,
+    y: -10_000.0,
+};
+
+/// Releases the primary button and removes the pointer. Egui doesn't count the release as a click,
+/// because the pointer first moves far away from where the button was pressed.
+fn release_without_click(egui_input: &mut RawInput) {
+    egui_input.events.push(Event::PointerMoved(POINTER_AWAY));
+    egui_input.events.push(Event::PointerButton {
+        pos: POINTER_AWAY,
+        button: PointerButton::Primary,
+        pressed: false,
+        modifiers: egui_input.modifiers,
+    });
+    egui_input.events.push(Event::PointerGone);
+}
+
+/// Tracks the active touches.
+///
+/// All touches are passed to egui as `Event::Touch`, from which egui derives multi-touch gestures
+/// like pinch zoom. The first touch also acts as the mouse pointer until a second finger touches,
+/// and moving two or more fingers scrolls.
+#[derive(Debug, Default)]
+struct TouchState {
+    /// Active touches in the order they started.
+    active: Vec<(u64, Pos2)>,
+    /// The touch that acts as the pointer.
+    primary: Option<u64>,
+}
+
+impl TouchState {
+    fn touch(
+        &mut self,
+        egui_input: &mut RawInput,
+        touch: &Touch,
+        pixels_per_point: f32,
+        config: &EguiConfig,
+        current_mouse_pos: &mut Pos2,
+    ) {
+        let pos = Pos2::new(
+            touch.location.x as f32 / pixels_per_point,
+            touch.location.y as f32 / pixels_per_point,
+        );
+        let phase = match touch.phase {
+            WTouchPhase::Started => TouchPhase::Start,
+            WTouchPhase::Moved => TouchPhase::Move,
+            WTouchPhase::Ended => TouchPhase::End,
+            WTouchPhase::Cancelled => TouchPhase::Cancel,
+        };
+
+        // winit's device IDs are opaque, but egui only needs to tell them apart.
+        let mut hasher = DefaultHasher::new();
+        touch.device_id.hash(&mut hasher);
+
+        egui_input.events.push(Event::Touch {
+            device_id: TouchDeviceId(hasher.finish()),
+            id: TouchId(touch.id),
+            phase,
+            pos,
+            force: touch.force.map_or(0.0, |force| force.normalized() as f32),
+        });
+
+        let is_primary = self.primary == Some(touch.id);
+        match phase {
+            TouchPhase::Start => {
+                let is_first = self.active.is_empty();
+                self.active.push((touch.id, pos));
+                if is_first {
+                    self.primary = Some(touch.id);
+                    *current_mouse_pos = pos;
+                    egui_input.events.push(Event::PointerMoved(pos));
+                    egui_input.events.push(Event::PointerButton {
+                        pos,
+                        button: PointerButton::Primary,
+                        pressed: true,
+                        modifiers: egui_input.modifiers,
+                    });
+                }
+                else if self.primary.take().is_some() {
+                    // Scrolling with two fingers shouldn't also drag or select under the first one,
+                    // so it stops being the pointer, like a cancelled touch.
+                    release_without_click(egui_input);
+                }
+            }
+            TouchPhase::Move => {
+                let old_centroid = self.centroid();
+                if let Some((_, active_pos)) =
+                    self.active.iter_mut().find(|(id, _)| *id == touch.id)
+                {
+                    *active_pos = pos;
+                }
+                if self.active.len() >= 2 {
+                    if let (Some(old), Some(new)) = (old_centroid, self.centroid()) {
+                        // The content follows the fingers.
+                        egui_input.add_scroll_delta(new - old, config);
+                    }
+                }
+                if is_primary {
+                    *current_mouse_pos = pos;
+                    egui_input.events.push(Event::PointerMoved(pos));
+                }
+            }
+            TouchPhase::End => {
+                self.active.retain(|(id, _)| *id != touch.id);
+                if is_primary {
+                    self.primary = None;
+                    egui_input.events.push(Event::PointerButton {
+                        pos,
+                        button: PointerButton::Primary,
+                        pressed: false,
+                        modifiers: egui_input.modifiers,
+                    });
+                    egui_input.events.push(Event::PointerGone);
+                }
+            }
+            TouchPhase::Cancel => {
+                self.active.retain(|(id, _)| *id != touch.id);
+                if is_primary {
+                    self.primary = None;
+                    release_without_click(egui_input);
+                }
+            }
+        }
     }
+
+    fn centroid(&self) -> Option<Pos2> {
+        if self.active.is_empty() {
+            return None;
+        }
+        let sum = self
+            .active
+            .iter()
+            .fold(Vec2::ZERO, |sum, (_, pos)| sum + pos.to_vec2());
+        Some((sum / self.active.len() as f32).to_pos2())
+    }
+}
+
+/// Maps winit key codes to egui keys. Generated by `keys.py`.
+///
+/// Numpad digits and `NumpadEnter` map to the same egui keys as their counterparts on the main
+/// keyboard. The key codes in `UNMAPPED` are intentionally left out, because egui has no key for
+/// them: function keys (`F1` to `F24`), modifiers (handled through `ModifiersChanged`), numpad
+/// operators, punctuation and symbols (which arrive as text through `ReceivedCharacter`), and
+/// media, browser and system keys.
+const KEY_MAP: &[(VirtualKeyCode, Key)] = &[
+    (VirtualKeyCode::Down, Key::ArrowDown),
+    (VirtualKeyCode::Left, Key::ArrowLeft),
+    (VirtualKeyCode::Right, Key::ArrowRight),
+    (VirtualKeyCode::Up, Key::ArrowUp),
+    (VirtualKeyCode::Escape, Key::Escape),
+    (VirtualKeyCode::Tab, Key::Tab),
+    (VirtualKeyCode::Back, Key::Backspace),
+    (VirtualKeyCode::Return, Key::Enter),
+    (VirtualKeyCode::NumpadEnter, Key::Enter),
+    (VirtualKeyCode::Space, Key::Space),
+    (VirtualKeyCode::Insert, Key::Insert),
+    (VirtualKeyCode::Delete, Key::Delete),
+    (VirtualKeyCode::Home, Key::Home),
+    (VirtualKeyCode::End, Key::End),
+    (VirtualKeyCode::PageUp, Key::PageUp),
+    (VirtualKeyCode::PageDown, Key::PageDown),
+    (VirtualKeyCode::Key0, Key::Num0),
+    (VirtualKeyCode::Numpad0, Key::Num0),
+    (VirtualKeyCode::Key1, Key::Num1),
+    (VirtualKeyCode::Numpad1, Key::Num1),
+    (VirtualKeyCode::Key2, Key::Num2),
+    (VirtualKeyCode::Numpad2, Key::Num2),
+    (VirtualKeyCode::Key3, Key::Num3),
+    (VirtualKeyCode::Numpad3, Key::Num3),
+    (VirtualKeyCode::Key4, Key::Num4),
+    (VirtualKeyCode::Numpad4, Key::Num4),
+    (VirtualKeyCode::Key5, Key::Num5),
+    (VirtualKeyCode::Numpad5, Key::Num5),
+    (VirtualKeyCode::Key6, Key::Num6),
+    (VirtualKeyCode::Numpad6, Key::Num6),
+    (VirtualKeyCode::Key7, Key::Num7),
+    (VirtualKeyCode::Numpad7, Key::Num7),
+    (VirtualKeyCode::Key8, Key::Num8),
+    (VirtualKeyCode::Numpad8, Key::Num8),
+    (VirtualKeyCode::Key9, Key::Num9),
+    (VirtualKeyCode::Numpad9, Key::Num9),
+    (VirtualKeyCode::A, Key::A),
+    (VirtualKeyCode::B, Key::B),
+    (VirtualKeyCode::C, Key::C),
+    (VirtualKeyCode::D, Key::D),
+    (VirtualKeyCode::E, Key::E),
+    (VirtualKeyCode::F, Key::F),
+    (VirtualKeyCode::G, Key::G),
+    (VirtualKeyCode::H, Key::H),
+    (VirtualKeyCode::I, Key::I),
+    (VirtualKeyCode::J, Key::J),
+    (VirtualKeyCode::K, Key::K),
+    (VirtualKeyCode::L, Key::L),
+    (VirtualKeyCode::M, Key::M),
+    (VirtualKeyCode::N, Key::N),
+    (VirtualKeyCode::O, Key::O),
+    (VirtualKeyCode::P, Key::P),
+    (VirtualKeyCode::Q, Key::Q),
+    (VirtualKeyCode::R, Key::R),
+    (VirtualKeyCode::S, Key::S),
+    (VirtualKeyCode::T, Key::T),
+    (VirtualKeyCode::U, Key::U),
+    (VirtualKeyCode::V, Key::V),
+    (VirtualKeyCode::W, Key::W),
+    (VirtualKeyCode::X, Key::X),
+    (VirtualKeyCode::Y, Key::Y),
+    (VirtualKeyCode::Z, Key::Z),
+];
+
+/// winit key codes without an egui key, i.e. all that aren't in `KEY_MAP`. Generated by `keys.py`.
+#[cfg(test)]
+const UNMAPPED: &[VirtualKeyCode] = &[
+    VirtualKeyCode::F1,
+    VirtualKeyCode::F2,
+    VirtualKeyCode::F3,
+    VirtualKeyCode::F4,
+    VirtualKeyCode::F5,
+    VirtualKeyCode::F6,
+    VirtualKeyCode::F7,
+    VirtualKeyCode::F8,
+    VirtualKeyCode::F9,
+    VirtualKeyCode::F10,
+    VirtualKeyCode::F11,
+    VirtualKeyCode::F12,
+    VirtualKeyCode::F13,
+    VirtualKeyCode::F14,
+    VirtualKeyCode::F15,
+    VirtualKeyCode::F16,
+    VirtualKeyCode::F17,
+    VirtualKeyCode::F18,
+    VirtualKeyCode::F19,
+    VirtualKeyCode::F20,
+    VirtualKeyCode::F21,
+    VirtualKeyCode::F22,
+    VirtualKeyCode::F23,
+    VirtualKeyCode::F24,
+    VirtualKeyCode::Snapshot,
+    VirtualKeyCode::Scroll,
+    VirtualKeyCode::Pause,
+    VirtualKeyCode::Compose,
+    VirtualKeyCode::Caret,
+    VirtualKeyCode::Numlock,
+    VirtualKeyCode::NumpadAdd,
+    VirtualKeyCode::NumpadDivide,
+    VirtualKeyCode::NumpadDecimal,
+    VirtualKeyCode::NumpadComma,
+    VirtualKeyCode::NumpadEquals,
+    VirtualKeyCode::NumpadMultiply,
+    VirtualKeyCode::NumpadSubtract,
+    VirtualKeyCode::AbntC1,
+    VirtualKeyCode::AbntC2,
+    VirtualKeyCode::Apostrophe,
+    VirtualKeyCode::Apps,
+    VirtualKeyCode::Asterisk,
+    VirtualKeyCode::At,
+    VirtualKeyCode::Ax,
+    VirtualKeyCode::Backslash,
+    VirtualKeyCode::Calculator,
+    VirtualKeyCode::Capital,
+    VirtualKeyCode::Colon,
+    VirtualKeyCode::Comma,
+    VirtualKeyCode::Convert,
+    VirtualKeyCode::Equals,
+    VirtualKeyCode::Grave,
+    VirtualKeyCode::Kana,
+    VirtualKeyCode::Kanji,
+    VirtualKeyCode::LAlt,
+    VirtualKeyCode::LBracket,
+    VirtualKeyCode::LControl,
+    VirtualKeyCode::LShift,
+    VirtualKeyCode::LWin,
+    VirtualKeyCode::Mail,
+    VirtualKeyCode::MediaSelect,
+    VirtualKeyCode::MediaStop,
+    VirtualKeyCode::Minus,
+    VirtualKeyCode::Mute,
+    VirtualKeyCode::MyComputer,
+    VirtualKeyCode::NavigateForward,
+    VirtualKeyCode::NavigateBackward,
+    VirtualKeyCode::NextTrack,
+    VirtualKeyCode::NoConvert,
+    VirtualKeyCode::OEM102,
+    VirtualKeyCode::Period,
+    VirtualKeyCode::PlayPause,
+    VirtualKeyCode::Plus,
+    VirtualKeyCode::Power,
+    VirtualKeyCode::PrevTrack,
+    VirtualKeyCode::RAlt,
+    VirtualKeyCode::RBracket,
+    VirtualKeyCode::RControl,
+    VirtualKeyCode::RShift,
+    VirtualKeyCode::RWin,
+    VirtualKeyCode::Semicolon,
+    VirtualKeyCode::Slash,
+    VirtualKeyCode::Sleep,
+    VirtualKeyCode::Stop,
+    VirtualKeyCode::Sysrq,
+    VirtualKeyCode::Underline,
+    VirtualKeyCode::Unlabeled,
+    VirtualKeyCode::VolumeDown,
+    VirtualKeyCode::VolumeUp,
+    VirtualKeyCode::Wake,
+    VirtualKeyCode::WebBack,
+    VirtualKeyCode::WebFavorites,
+    VirtualKeyCode::WebForward,
+    VirtualKeyCode::WebHome,
+    VirtualKeyCode::WebRefresh,
+    VirtualKeyCode::WebSearch,
+    VirtualKeyCode::WebStop,
+    VirtualKeyCode::Yen,
+    VirtualKeyCode::Copy,
+    VirtualKeyCode::Paste,
+    VirtualKeyCode::Cut,
+];
+
+fn convert_key(key: &VirtualKeyCode) -> Option<Key> {
+    KEY_MAP
+        .iter()
+        .find(|(winit_key, _)| winit_key == key)
+        .map(|(_, egui_key)| *egui_key)
 }
 
 fn convert_modifiers(modifiers: &ModifiersState) -> Modifiers {
@@ -463,8 +1226,13 @@ fn convert_modifiers(modifiers: &ModifiersState) -> Modifiers {
         alt: modifiers.alt(),
         ctrl: modifiers.ctrl(),
         shift: modifiers.shift(),
-        mac_cmd: modifiers.logo(),
-        command: modifiers.logo(),
+        mac_cmd: cfg!(target_os = "macos") && modifiers.logo(),
+        command: if cfg!(target_os = "macos") {
+            modifiers.logo()
+        }
+        else {
+            modifiers.ctrl()
+        },
     }
 }
 
@@ -514,3 +1282,436 @@ fn set_cursor(window: &Window, cursor_icon: CursorIcon) {
     window.set_cursor_icon(icon);
     window.set_cursor_visible(true);
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use amethyst_core::{
+        dispatcher::DispatcherBuilder,
+        ecs::{
+            Resources,
+            World,
+        },
+    };
+    use egui::{
+        WidgetInfo,
+        WidgetType,
+    };
+
+    use super::*;
+    use crate::speech::SpeechBackend;
+
+    /// Every egui key. Adding a key to egui breaks the exhaustive match in `key_map_is_complete`,
+    /// so that this list and `keys.py` are updated.
+    const ALL_KEYS: &[Key] = &[
+        Key::ArrowDown,
+        Key::ArrowLeft,
+        Key::ArrowRight,
+        Key::ArrowUp,
+        Key::Escape,
+        Key::Tab,
+        Key::Backspace,
+        Key::Enter,
+        Key::Space,
+        Key::Insert,
+        Key::Delete,
+        Key::Home,
+        Key::End,
+        Key::PageUp,
+        Key::PageDown,
+        Key::Num0,
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+    ];
+
+    fn touch(id: u64, phase: WTouchPhase, x: f64, y: f64) -> Touch {
+        Touch {
+            device_id: unsafe { winit::event::DeviceId::dummy() },
+            phase,
+            location: PhysicalPosition::new(x, y),
+            force: None,
+            id,
+        }
+    }
+
+    fn feed_touches(touches: &[Touch]) -> RawInput {
+        let mut state = TouchState::default();
+        let mut egui_input = RawInput::default();
+        let mut current_mouse_pos = Pos2::ZERO;
+        for touch in touches {
+            state.touch(
+                &mut egui_input,
+                touch,
+                2.0,
+                &EguiConfig::default(),
+                &mut current_mouse_pos,
+            );
+        }
+        egui_input
+    }
+
+    fn pointer_buttons(egui_input: &RawInput) -> Vec<bool> {
+        egui_input
+            .events
+            .iter()
+            .filter_map(|event| {
+                match event {
+                    Event::PointerButton { pressed, .. } => Some(*pressed),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn touch_acts_as_pointer() {
+        let egui_input = feed_touches(&[
+            touch(1, WTouchPhase::Started, 20.0, 20.0),
+            touch(1, WTouchPhase::Moved, 40.0, 60.0),
+            touch(1, WTouchPhase::Ended, 40.0, 60.0),
+        ]);
+
+        assert_eq!(pointer_buttons(&egui_input), vec![true, false]);
+        assert!(egui_input
+            .events
+            .contains(&Event::PointerMoved(Pos2::new(20.0, 30.0))));
+        assert!(matches!(egui_input.events.last(), Some(Event::PointerGone)));
+    }
+
+    /// Checks that the primary button was pressed and then released away from the press, so that
+    /// it's no click, and that the pointer stays gone afterwards.
+    fn assert_released_without_click(egui_input: &RawInput) {
+        assert_eq!(pointer_buttons(egui_input), vec![true, false]);
+
+        let release = egui_input
+            .events
+            .iter()
+            .position(|event| matches!(event, Event::PointerButton { pressed: false, .. }))
+            .unwrap();
+        assert!(matches!(
+            egui_input.events[release],
+            Event::PointerButton { pos, .. } if pos == POINTER_AWAY
+        ));
+        assert_eq!(
+            egui_input.events[release - 1],
+            Event::PointerMoved(POINTER_AWAY)
+        );
+        assert!(!egui_input.events[release + 1..]
+            .iter()
+            .any(|event| matches!(event, Event::PointerMoved(_) | Event::PointerButton { .. })));
+        assert!(matches!(egui_input.events.last(), Some(Event::PointerGone)));
+    }
+
+    #[test]
+    fn cancelled_touch_releases_without_click() {
+        let egui_input = feed_touches(&[
+            touch(1, WTouchPhase::Started, 20.0, 20.0),
+            touch(1, WTouchPhase::Cancelled, 20.0, 20.0),
+        ]);
+
+        assert_released_without_click(&egui_input);
+    }
+
+    #[test]
+    fn two_finger_move_scrolls() {
+        let egui_input = feed_touches(&[
+            touch(1, WTouchPhase::Started, 0.0, 0.0),
+            touch(2, WTouchPhase::Started, 200.0, 0.0),
+            touch(1, WTouchPhase::Moved, 0.0, 20.0),
+            touch(2, WTouchPhase::Moved, 200.0, 20.0),
+            touch(1, WTouchPhase::Ended, 0.0, 20.0),
+            touch(2, WTouchPhase::Ended, 200.0, 20.0),
+        ]);
+
+        assert_eq!(egui_input.scroll_delta, Vec2::new(0.0, 10.0));
+        // The first touch stops being the pointer, when the second one starts.
+        assert_released_without_click(&egui_input);
+    }
+
+    #[test]
+    fn with_ui_outside_of_frame() {
+        let mut egui_ctx = EguiContext::<DefaultEgui>::default();
+        assert!(egui_ctx.with_ui(|_| ()).is_none());
+
+        egui_ctx.stage = EguiStage::End(Output::default());
+        assert!(egui_ctx.with_ui(|_| ()).is_none());
+
+        egui_ctx.stage = EguiStage::Render;
+        assert_eq!(egui_ctx.with_ui(|_| 42), Some(42));
+    }
+
+    /// A UI system added directly to the dispatcher instead of through
+    /// `EguiBundle::with_ui_system`. It records whether it got the egui context.
+    struct MisorderedUiSystem;
+
+    impl System for MisorderedUiSystem {
+        fn build(self) -> Box<dyn ParallelRunnable + 'static> {
+            Box::new(
+                SystemBuilder::new("MisorderedUiSystem")
+                    .read_resource::<EguiContext>()
+                    .write_resource::<Vec<bool>>()
+                    .build(|_commands, _world, (egui_ctx, got_ui), _queries| {
+                        got_ui.push(egui_ctx.with_ui(|_| ()).is_some());
+                    }),
+            )
+        }
+    }
+
+    #[test]
+    fn misordered_ui_system_gets_no_context() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(EguiContext::<DefaultEgui>::default());
+        resources.insert(Vec::<bool>::new());
+
+        let mut builder = DispatcherBuilder::default();
+        builder.add_system(MisorderedUiSystem);
+        let mut dispatcher = builder.build(&mut world, &mut resources).unwrap();
+
+        // No `EguiSystem` started a frame before the system ran.
+        dispatcher.execute(&mut world, &mut resources);
+        dispatcher.execute(&mut world, &mut resources);
+        assert_eq!(*resources.get::<Vec<bool>>().unwrap(), vec![false, false]);
+        assert!(resources
+            .get::<EguiContext>()
+            .unwrap()
+            .warned_outside_frame
+            .load(Ordering::Relaxed));
+    }
+
+    /// Speech backend recording the utterances and rates it is given.
+    #[derive(Clone, Default)]
+    struct RecordingSpeech {
+        spoken: Arc<Mutex<Vec<(String, bool)>>>,
+        rates: Arc<Mutex<Vec<f32>>>,
+    }
+
+    impl SpeechBackend for RecordingSpeech {
+        fn speak(&mut self, text: &str, interrupt: bool) {
+            self.spoken
+                .lock()
+                .unwrap()
+                .push((text.to_owned(), interrupt));
+        }
+
+        fn set_rate(&mut self, rate: f32) {
+            self.rates.lock().unwrap().push(rate);
+        }
+    }
+
+    #[test]
+    fn only_first_description_interrupts() {
+        let recording = RecordingSpeech::default();
+        let mut speech = EguiSpeech::new(recording.clone());
+        let config = EguiConfig {
+            tts_rate: 1.5,
+            ..Default::default()
+        };
+
+        let ok = WidgetInfo::labeled(WidgetType::Button, "OK");
+        let volume = WidgetInfo::labeled(WidgetType::Slider, "Volume");
+        speak_output_events(
+            vec![
+                OutputEvent::Clicked(ok.clone()),
+                OutputEvent::ValueChanged(volume.clone()),
+                OutputEvent::FocusGained(ok.clone()),
+            ],
+            &config,
+            &mut speech,
+        );
+        speak_output_events(
+            vec![OutputEvent::DoubleClicked(volume.clone())],
+            &config,
+            &mut speech,
+        );
+
+        assert_eq!(
+            *recording.spoken.lock().unwrap(),
+            vec![
+                (ok.description(), true),
+                (volume.description(), false),
+                (ok.description(), false),
+                (volume.description(), true),
+            ]
+        );
+        // The rate only changed once.
+        assert_eq!(*recording.rates.lock().unwrap(), vec![1.5]);
+    }
+
+    #[test]
+    fn queued_descriptions_dont_interrupt() {
+        let recording = RecordingSpeech::default();
+        let mut speech = EguiSpeech::new(recording.clone());
+        let config = EguiConfig {
+            tts_interrupt: false,
+            ..Default::default()
+        };
+
+        let ok = WidgetInfo::labeled(WidgetType::Button, "OK");
+        speak_output_events(
+            vec![
+                OutputEvent::Clicked(ok.clone()),
+                OutputEvent::Clicked(ok.clone()),
+            ],
+            &config,
+            &mut speech,
+        );
+
+        assert_eq!(
+            *recording.spoken.lock().unwrap(),
+            vec![(ok.description(), false), (ok.description(), false)]
+        );
+    }
+
+    #[test]
+    fn key_map_is_complete() {
+        for key in ALL_KEYS {
+            match key {
+                Key::ArrowDown
+                | Key::ArrowLeft
+                | Key::ArrowRight
+                | Key::ArrowUp
+                | Key::Escape
+                | Key::Tab
+                | Key::Backspace
+                | Key::Enter
+                | Key::Space
+                | Key::Insert
+                | Key::Delete
+                | Key::Home
+                | Key::End
+                | Key::PageUp
+                | Key::PageDown
+                | Key::Num0
+                | Key::Num1
+                | Key::Num2
+                | Key::Num3
+                | Key::Num4
+                | Key::Num5
+                | Key::Num6
+                | Key::Num7
+                | Key::Num8
+                | Key::Num9
+                | Key::A
+                | Key::B
+                | Key::C
+                | Key::D
+                | Key::E
+                | Key::F
+                | Key::G
+                | Key::H
+                | Key::I
+                | Key::J
+                | Key::K
+                | Key::L
+                | Key::M
+                | Key::N
+                | Key::O
+                | Key::P
+                | Key::Q
+                | Key::R
+                | Key::S
+                | Key::T
+                | Key::U
+                | Key::V
+                | Key::W
+                | Key::X
+                | Key::Y
+                | Key::Z => {}
+            }
+            assert!(
+                KEY_MAP.iter().any(|(_, egui_key)| egui_key == key),
+                "{:?} is not mapped",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn key_map_has_no_duplicates() {
+        for (i, (winit_key, _)) in KEY_MAP.iter().enumerate() {
+            assert!(
+                KEY_MAP[i + 1..].iter().all(|(other, _)| other != winit_key),
+                "{:?} is mapped twice",
+                winit_key
+            );
+        }
+    }
+
+    #[test]
+    fn every_winit_key_is_mapped_or_unmapped() {
+        // `VirtualKeyCode` has consecutive discriminants and `Cut` is its last variant.
+        for code in 0..=VirtualKeyCode::Cut as u32 {
+            let mapped = KEY_MAP
+                .iter()
+                .filter(|(winit_key, _)| *winit_key as u32 == code)
+                .count();
+            let unmapped = UNMAPPED
+                .iter()
+                .filter(|winit_key| **winit_key as u32 == code)
+                .count();
+            assert_eq!(
+                mapped + unmapped,
+                1,
+                "key code {} is in KEY_MAP {} times and in UNMAPPED {} times",
+                code,
+                mapped,
+                unmapped
+            );
+        }
+    }
+
+    #[test]
+    fn numpad_keys_map_like_main_keys() {
+        let numpad = [
+            (VirtualKeyCode::Numpad0, Key::Num0),
+            (VirtualKeyCode::Numpad1, Key::Num1),
+            (VirtualKeyCode::Numpad2, Key::Num2),
+            (VirtualKeyCode::Numpad3, Key::Num3),
+            (VirtualKeyCode::Numpad4, Key::Num4),
+            (VirtualKeyCode::Numpad5, Key::Num5),
+            (VirtualKeyCode::Numpad6, Key::Num6),
+            (VirtualKeyCode::Numpad7, Key::Num7),
+            (VirtualKeyCode::Numpad8, Key::Num8),
+            (VirtualKeyCode::Numpad9, Key::Num9),
+            (VirtualKeyCode::NumpadEnter, Key::Enter),
+        ];
+        for (winit_key, egui_key) in &numpad {
+            assert_eq!(convert_key(winit_key), Some(*egui_key));
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::BTreeMap,
+    marker::PhantomData,
+};
+
+use amethyst_assets::{
+    register_asset_type,
+    register_importer,
+    Asset,
+    AssetProcessorSystem,
+    AssetStorage,
+    DefaultLoader,
+    Format,
+    Handle,
+    Loader,
+    ProcessableAsset,
+    ProcessingState,
+};
+use amethyst_core::ecs::{
+    ParallelRunnable,
+    System,
+    SystemBuilder,
+};
+use amethyst_error::Error;
+use derivative::Derivative;
+use egui::{
+    FontFamily,
+    Style,
+    TextStyle,
+    Visuals,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use type_uuid::TypeUuid;
+
+use crate::{
+    fonts::EguiFonts,
+    system::{
+        EguiContext,
+        EguiKey,
+    },
+};
+
+/// The built-in themes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemePreset {
+    Dark,
+    Light,
+}
+
+impl ThemePreset {
+    fn visuals(self) -> Visuals {
+        match self {
+            ThemePreset::Dark => Visuals::dark(),
+            ThemePreset::Light => Visuals::light(),
+        }
+    }
+}
+
+/// The contents of a `.theme` file.
+///
+/// ```ron
+/// (
+///     preset: Some(Light),
+///     style: Some((
+///         spacing: (item_spacing: (8.0, 4.0)),
+///     )),
+///     text_styles: {
+///         Body: (Proportional, 16.0),
+///     },
+/// )
+/// ```
+///
+/// All fields are optional. Missing fields of `style` keep egui's defaults. The visuals of
+/// `preset` replace the visuals of `style`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "5b8e2f47-c1a3-4d96-b07e-9a4c6d1f2e83"]
+#[serde(default)]
+pub struct EguiThemeData {
+    pub preset: Option<ThemePreset>,
+    pub style: Option<Style>,
+    /// Font family and size of text styles. Applied through the `EguiFonts` resource.
+    pub text_styles: BTreeMap<TextStyle, (FontFamily, f32)>,
+}
+
+/// The style of egui contexts. Select it with the `EguiTheme` resource.
+#[derive(Clone, Debug, TypeUuid)]
+#[uuid = "e31f7c09-4a62-48d5-b2c1-7d0e5a9b3f16"]
+pub struct EguiThemeAsset {
+    style: Style,
+    text_styles: BTreeMap<TextStyle, (FontFamily, f32)>,
+}
+
+impl From<EguiThemeData> for EguiThemeAsset {
+    fn from(data: EguiThemeData) -> Self {
+        let mut style = data.style.unwrap_or_default();
+        if let Some(preset) = data.preset {
+            style.visuals = preset.visuals();
+        }
+        Self {
+            style,
+            text_styles: data.text_styles,
+        }
+    }
+}
+
+impl From<ThemePreset> for EguiThemeAsset {
+    fn from(preset: ThemePreset) -> Self {
+        EguiThemeData {
+            preset: Some(preset),
+            ..EguiThemeData::default()
+        }
+        .into()
+    }
+}
+
+impl Asset for EguiThemeAsset {
+    type Data = EguiThemeData;
+
+    fn name() -> &'static str {
+        "egui::Theme"
+    }
+}
+
+impl ProcessableAsset for EguiThemeAsset {
+    fn process(
+        data: EguiThemeData,
+    ) -> Result<ProcessingState<EguiThemeData, EguiThemeAsset>, Error> {
+        Ok(ProcessingState::Loaded(data.into()))
+    }
+}
+
+register_asset_type!(EguiThemeData => EguiThemeAsset; AssetProcessorSystem<EguiThemeAsset>);
+
+/// Imports `.theme` files, which contain an `EguiThemeData` in RON.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, TypeUuid)]
+#[uuid = "96c4a0d8-3e1b-4f7a-8d25-c0b7e1f4a963"]
+pub struct ThemeFormat;
+
+register_importer!(".theme", ThemeFormat);
+
+impl Format<EguiThemeData> for ThemeFormat {
+    fn name(&self) -> &'static str {
+        "THEME"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<EguiThemeData, Error> {
+        ron::de::from_bytes(&bytes).map_err(Error::new)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum ThemeSource {
+    Preset(ThemePreset),
+    Path(String),
+    Handle(Handle<EguiThemeAsset>),
+}
+
+/// Resource selecting the theme of all egui contexts. Themes loaded from files are applied again
+/// whenever the file changes.
+///
+/// Contexts that keep their own style opt out with `EguiBundle::without_theme`. The text styles of
+/// a theme are part of `EguiFonts`, so they apply to all contexts.
+#[derive(Clone, Debug)]
+pub struct EguiTheme {
+    source: ThemeSource,
+    /// Incremented on every change, so that the contexts know when to update their style.
+    generation: u64,
+}
+
+impl Default for EguiTheme {
+    fn default() -> Self {
+        Self::preset(ThemePreset::Dark)
+    }
+}
+
+impl EguiTheme {
+    pub fn preset(preset: ThemePreset) -> Self {
+        Self {
+            source: ThemeSource::Preset(preset),
+            generation: 0,
+        }
+    }
+
+    /// Loads the theme from the `.theme` file at `path` with the `DefaultLoader`.
+    pub fn from_path(path: impl Into<String>) -> Self {
+        Self {
+            source: ThemeSource::Path(path.into()),
+            generation: 0,
+        }
+    }
+
+    pub fn set_preset(&mut self, preset: ThemePreset) {
+        self.set_source(ThemeSource::Preset(preset));
+    }
+
+    /// Switches to the theme in the `.theme` file at `path`.
+    pub fn set_path(&mut self, path: impl Into<String>) {
+        self.set_source(ThemeSource::Path(path.into()));
+    }
+
+    /// Switches to an already loaded theme.
+    pub fn set_handle(&mut self, handle: Handle<EguiThemeAsset>) {
+        self.set_source(ThemeSource::Handle(handle));
+    }
+
+    /// Returns the selected preset, or `None` if a theme file is selected.
+    pub fn current_preset(&self) -> Option<ThemePreset> {
+        match self.source {
+            ThemeSource::Preset(preset) => Some(preset),
+            _ => None,
+        }
+    }
+
+    fn set_source(&mut self, source: ThemeSource) {
+        self.source = source;
+        self.generation += 1;
+    }
+
+    /// Starts loading the theme, if it was selected by path.
+    fn load(&mut self, loader: &DefaultLoader) {
+        if let ThemeSource::Path(path) = &self.source {
+            self.source = ThemeSource::Handle(loader.load(path));
+        }
+    }
+}
+
+/// Applies `EguiTheme` to the egui context `K`.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Default(bound = ""))]
+pub(crate) struct EguiThemeSystem<K> {
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<K>,
+}
+
+impl<K: EguiKey> System for EguiThemeSystem<K> {
+    fn build(self) -> Box<dyn ParallelRunnable + 'static> {
+        // Generation and asset version last applied to the context.
+        let mut applied: Option<(u64, Option<u32>)> = None;
+
+        Box::new(
+            SystemBuilder::new(format!("EguiThemeSystem<{}>", std::any::type_name::<K>()))
+                .read_resource::<DefaultLoader>()
+                .read_resource::<AssetStorage<EguiThemeAsset>>()
+                .write_resource::<EguiTheme>()
+                .write_resource::<EguiFonts>()
+                .read_resource::<EguiContext<K>>()
+                .build(move |_commands, _world, resources, _queries| {
+                    let (loader, storage, theme, fonts, egui_ctx) = resources;
+
+                    theme.load(loader);
+
+                    let version = match &theme.source {
+                        ThemeSource::Preset(_) => None,
+                        ThemeSource::Handle(handle) => {
+                            match storage.get_asset_with_version(handle) {
+                                Some((_, version)) => Some(version),
+                                // Keep the old style while the theme is loading.
+                                None => return,
+                            }
+                        }
+                        ThemeSource::Path(_) => return,
+                    };
+                    if applied == Some((theme.generation, version)) {
+                        return;
+                    }
+
+                    let asset = match &theme.source {
+                        ThemeSource::Preset(preset) => EguiThemeAsset::from(*preset),
+                        ThemeSource::Handle(handle) => {
+                            match storage.get(handle) {
+                                Some(asset) => asset.clone(),
+                                None => return,
+                            }
+                        }
+                        ThemeSource::Path(_) => return,
+                    };
+
+                    log::debug!("Applying theme: version={:?}", version);
+                    egui_ctx.ctx.set_style(asset.style);
+                    fonts.set_theme_text_styles(asset.text_styles);
+                    applied = Some((theme.generation, version));
+                }),
+        )
+    }
+}
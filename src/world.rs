@@ -0,0 +1,70 @@
+use std::marker::PhantomData;
+
+use amethyst_core::math::{
+    Matrix4,
+    Vector3,
+};
+use derivative::Derivative;
+use egui::{
+    Pos2,
+    Rect,
+    Vec2,
+};
+
+use crate::system::DefaultEgui;
+
+/// Renders a part of the UI of the egui context with key `K` as a quad in the world, positioned by
+/// the entity's `Transform`.
+///
+/// Use this together with `RenderEgui::in_world` and `EguiBundle::with_offscreen_size`, which sets
+/// the size of the whole UI in points. The quad is centered on the entity and faces along its
+/// positive Z axis.
+///
+/// All panels of a context share its UI: each panel shows the rectangle of `size` points at
+/// `origin`. To show different content on each entity, e.g. health bars or nameplates, give every
+/// panel its own `origin` and lay out its content there, e.g. with
+/// `egui::Area::new(id).fixed_pos(panel.origin)`. Panels of different contexts use different key
+/// types.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Copy(bound = ""), Debug(bound = ""))]
+pub struct EguiWorldPanel<K = DefaultEgui> {
+    /// Size of the panel in UI points.
+    pub size: Vec2,
+    /// How many UI points make up one world unit.
+    pub pixels_per_unit: f32,
+    /// Top left corner of the part of the UI shown on this panel, in points.
+    pub origin: Pos2,
+    #[derivative(Debug = "ignore")]
+    _key: PhantomData<K>,
+}
+
+impl<K> EguiWorldPanel<K> {
+    pub fn new(size: Vec2, pixels_per_unit: f32) -> Self {
+        Self {
+            size,
+            pixels_per_unit,
+            origin: Pos2::ZERO,
+            _key: PhantomData,
+        }
+    }
+
+    /// Shows the part of the UI at `origin`, instead of the top left corner.
+    pub fn with_origin(mut self, origin: Pos2) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Returns the part of the UI shown on this panel, in points.
+    pub fn rect(&self) -> Rect {
+        Rect::from_min_size(self.origin, self.size)
+    }
+
+    /// Returns the matrix transforming UI points into the entity's local space.
+    pub(crate) fn local_matrix(&self) -> Matrix4<f32> {
+        let scale = 1.0 / self.pixels_per_unit;
+        let center = self.rect().center();
+        // Egui's Y axis points down, so we flip it.
+        Matrix4::new_nonuniform_scaling(&Vector3::new(scale, -scale, scale))
+            * Matrix4::new_translation(&Vector3::new(-center.x, -center.y, 0.0))
+    }
+}